@@ -0,0 +1,114 @@
+use crate::formats::{find_gif_end, find_jpeg_end, read_png_details, read_webp_details};
+use crate::{detect_all, ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ReadInterface};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Seek, SeekFrom};
+use std::path::Path;
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TrailingData {
+    pub offset: u64,
+    pub length: u64,
+}
+
+// Every format the data is valid as, and anything stored after the end of the
+// image, to spot polyglot files
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Analysis {
+    // in detection order, the first one is what ImageInfo reports
+    pub matches: Vec<ImageInfo>,
+    // end of the first match as described by its own structure: JPEG EOI, PNG
+    // IEND, GIF trailer or RIFF size. None for other formats or if not found.
+    pub logical_end: Option<u64>,
+    // bytes after logical_end, e.g. an appended archive or the secondary
+    // images of a JPEG MPF file
+    pub trailing_data: Option<TrailingData>,
+}
+
+fn find_logical_end<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    format: ImageFormat,
+) -> ImageInfoResult<Option<usize>>
+where
+    R: BufRead + Seek,
+{
+    match format {
+        ImageFormat::JPEG => find_jpeg_end(ri, length),
+        ImageFormat::GIF => find_gif_end(ri, length),
+        ImageFormat::PNG => {
+            let details = read_png_details(ri, length)?;
+            Ok(match details.chunks.last() {
+                Some(iend) if details.has_iend => {
+                    // length, type and CRC around the data
+                    Some(iend.offset as usize + 12 + iend.length as usize)
+                }
+                _ => None,
+            })
+        }
+        ImageFormat::WEBP => {
+            let details = read_webp_details(ri, length)?;
+            let riff_size = details.riff_size as usize;
+            // the RIFF chunk is padded to an even size
+            let end = 8 + riff_size + (riff_size & 1);
+            Ok(if end <= length { Some(end) } else { None })
+        }
+        _ => Ok(None),
+    }
+}
+
+impl Analysis {
+    // More than one format matches, or data follows the end of the image
+    pub fn is_polyglot(&self) -> bool {
+        self.matches.len() > 1 || self.trailing_data.is_some()
+    }
+
+    pub fn from_reader<R>(reader: &mut R) -> ImageInfoResult<Analysis>
+    where
+        R: BufRead + Seek,
+    {
+        let length = reader.seek(SeekFrom::End(0))? as usize;
+        let mut ri = ReadInterface::from_reader(reader, length);
+        let matches = detect_all(&mut ri, length);
+        let format = match matches.first() {
+            Some(info) => info.format,
+            None => return Err(ImageInfoError::UnrecognizedFormat),
+        };
+
+        // a detected file whose structure can't be followed has no known end
+        let logical_end = match find_logical_end(&mut ri, length, format) {
+            Err(ImageInfoError::UnrecognizedFormat) => None,
+            logical_end => logical_end?,
+        };
+        let trailing_data = match logical_end {
+            Some(end) if end < length => Some(TrailingData {
+                offset: end as u64,
+                length: (length - end) as u64,
+            }),
+            _ => None,
+        };
+        Ok(Analysis {
+            matches,
+            logical_end: logical_end.map(|end| end as u64),
+            trailing_data,
+        })
+    }
+
+    pub fn from_file(file: &File) -> ImageInfoResult<Analysis> {
+        let mut reader = BufReader::new(file);
+        Self::from_reader(&mut reader)
+    }
+
+    pub fn from_file_path(filepath: impl AsRef<Path>) -> ImageInfoResult<Analysis> {
+        let file = File::open(filepath)?;
+        Self::from_file(&file)
+    }
+
+    pub fn from_raw_data(data: &[u8]) -> ImageInfoResult<Analysis> {
+        let mut reader = BufReader::new(Cursor::new(data));
+        Self::from_reader(&mut reader)
+    }
+}
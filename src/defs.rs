@@ -1,6 +1,8 @@
-use serde::Serialize;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ImageSize {
     pub width: i64,
     pub height: i64,
@@ -38,6 +40,7 @@ impl From<std::io::Error> for ImageInfoError {
     }
 }
 
+#[cfg(feature = "serde")]
 impl Serialize for ImageInfoError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
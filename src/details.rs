@@ -0,0 +1,706 @@
+use crate::formats::{
+    read_bmp_details, read_gif_details, read_heif_details, read_icns_entries, read_ico_entries,
+    read_jpeg2000_details, read_jpeg_details, read_png_details, read_psd_details, read_qoi_details,
+    read_tga_details, read_tiff_pages, read_webp_details,
+};
+use crate::heif::read_heif_meta;
+use crate::{detect, ImageFormat, ImageInfoResult, ImageSize, ReadInterface};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Seek, SeekFrom};
+use std::path::Path;
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum PsdColorMode {
+    Bitmap,
+    Grayscale,
+    Indexed,
+    Rgb,
+    Cmyk,
+    Multichannel,
+    Duotone,
+    Lab,
+    Unknown(u16),
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PsdImageResource {
+    pub id: u16,
+    pub name: String,
+    // absolute offset and length of the resource data
+    pub offset: u64,
+    pub length: u32,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PsdDetails {
+    // 1 for PSD, 2 for PSB (large document format)
+    pub version: u16,
+    pub channels: u16,
+    // bits per channel: 1, 8, 16 or 32
+    pub depth: u16,
+    pub color_mode: PsdColorMode,
+    // None if the layer info can't be read
+    pub layer_count: Option<u16>,
+    pub image_resources: Vec<PsdImageResource>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum QoiColorspace {
+    // sRGB color channels, linear alpha
+    SrgbLinearAlpha,
+    Linear,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct QoiDetails {
+    // 3 RGB, 4 RGBA
+    pub channels: u8,
+    pub colorspace: QoiColorspace,
+    // false if the file doesn't end with the 8-byte end marker, e.g. truncated
+    pub has_end_marker: bool,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TgaColorMap {
+    pub first_entry: u16,
+    pub length: u16,
+    // bits per entry
+    pub entry_size: u8,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum TgaOrigin {
+    BottomLeft,
+    BottomRight,
+    TopLeft,
+    TopRight,
+}
+
+// TGA 2.0 extension area
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TgaExtension {
+    pub author_name: String,
+    pub software_id: String,
+    // e.g. "1.50b"
+    pub software_version: Option<String>,
+    // numerator and denominator
+    pub pixel_aspect_ratio: Option<(u16, u16)>,
+    pub gamma: Option<f64>,
+    // 0 no alpha, 1-2 undefined, 3 alpha, 4 premultiplied alpha
+    pub attributes_type: u8,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TgaDetails {
+    // 1-3 uncompressed color-mapped, true-color and grayscale, 9-11 RLE
+    pub image_type: u8,
+    pub image_id: Option<String>,
+    pub color_map: Option<TgaColorMap>,
+    pub pixel_depth: u8,
+    // attribute bits per pixel from the image descriptor
+    pub alpha_bits: u8,
+    // first pixel of the image data
+    pub origin: TgaOrigin,
+    // only with the TGA 2.0 footer
+    pub extension: Option<TgaExtension>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TiffPage {
+    pub size: ImageSize,
+    // NewSubfileType bits, 0 for a plain full resolution image
+    pub subfile_type: u32,
+}
+
+impl TiffPage {
+    pub fn is_reduced_resolution(&self) -> bool {
+        self.subfile_type & 1 != 0
+    }
+
+    pub fn is_page(&self) -> bool {
+        self.subfile_type & 2 != 0
+    }
+
+    pub fn is_mask(&self) -> bool {
+        self.subfile_type & 4 != 0
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TiffDetails {
+    // IFDs of the main chain in file order
+    pub pages: Vec<TiffPage>,
+}
+
+impl TiffDetails {
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum BmpHeaderVersion {
+    // OS/2 1.x BITMAPCOREHEADER, 16-bit dimensions
+    Core,
+    // OS/2 2.x, 16 to 64 bytes
+    Os2V2,
+    Info,
+    V2,
+    V3,
+    V4,
+    V5,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum BmpCompression {
+    Rgb,
+    Rle8,
+    Rle4,
+    Bitfields,
+    Jpeg,
+    Png,
+    AlphaBitfields,
+    // OS/2 2.x only
+    Huffman1D,
+    Rle24,
+    Cmyk,
+    CmykRle8,
+    CmykRle4,
+    Unknown(u32),
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct BmpIccProfile {
+    // absolute offset and length of the embedded profile
+    pub offset: u64,
+    pub length: u64,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct BmpDetails {
+    pub header_version: BmpHeaderVersion,
+    pub header_size: u32,
+    pub bit_count: u16,
+    pub compression: BmpCompression,
+    // number of color table entries, 0 without a palette
+    pub palette_size: u32,
+    // rows stored from the top, written as a negative height
+    pub top_down: bool,
+    // offset of the pixel data
+    pub data_offset: u32,
+    // V5 PROFILE_EMBEDDED color space
+    pub icc_profile: Option<BmpIccProfile>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct GifFrame {
+    // image descriptor rectangle within the logical screen
+    pub left: u16,
+    pub top: u16,
+    pub width: u16,
+    pub height: u16,
+    pub interlaced: bool,
+    // number of entries, None without a local color table
+    pub local_color_table_size: Option<u16>,
+    // graphic control extension: 0 unspecified, 1 keep, 2 restore to
+    // background, 3 restore to previous
+    pub disposal_method: u8,
+    // in hundredths of a second
+    pub delay: u16,
+    pub transparent_index: Option<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct GifDetails {
+    // "87a" or "89a"
+    pub version: String,
+    // number of entries, None without a global color table
+    pub global_color_table_size: Option<u16>,
+    pub background_color_index: u8,
+    // aspect ratio is (value + 15) / 64, 0 if not given
+    pub pixel_aspect_ratio: u8,
+    pub frames: Vec<GifFrame>,
+    // identifier and authentication code, e.g. "NETSCAPE2.0"
+    pub application_extensions: Vec<String>,
+    // NETSCAPE2.0 / ANIMEXTS1.0 loop count, 0 loops forever
+    pub loop_count: Option<u16>,
+    pub xmp: Option<String>,
+    // false if the file ends or breaks before the trailer
+    pub has_trailer: bool,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct HeifImageItem {
+    pub id: u32,
+    pub item_type: String,
+    // displayed size, None if the item carries no ispe
+    pub size: Option<ImageSize>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct HeifGrid {
+    pub rows: u32,
+    pub columns: u32,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum HeifAuxiliaryKind {
+    Alpha,
+    Depth,
+    GainMap,
+    Other,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct HeifAuxiliaryItem {
+    pub item: HeifImageItem,
+    pub kind: HeifAuxiliaryKind,
+    // auxC URN, None for gain maps of a tmap item
+    pub aux_type: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct HeifDetails {
+    pub primary_item: HeifImageItem,
+    // layout of a grid primary item
+    pub grid: Option<HeifGrid>,
+    // inputs of a grid or iovl primary item, in reference order
+    pub tiles: Vec<HeifImageItem>,
+    pub auxiliary_items: Vec<HeifAuxiliaryItem>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum IcoPayload {
+    Png,
+    Dib,
+    // out of range or not recognized
+    Unknown,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CursorHotspot {
+    pub x: u16,
+    pub y: u16,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct IcoEntry {
+    // read from the payload, the directory only if the payload is unknown
+    pub size: ImageSize,
+    pub color_count: u8,
+    // icons only
+    pub planes: Option<u16>,
+    pub bit_count: Option<u16>,
+    // cursors only
+    pub hotspot: Option<CursorHotspot>,
+    pub byte_size: u32,
+    pub offset: u32,
+    pub payload: IcoPayload,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct IcoDetails {
+    // directory entries in file order
+    pub entries: Vec<IcoEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum IcnsPayload {
+    Png,
+    Jpeg2000,
+    Argb,
+    // packbits compressed RGB
+    Rle,
+    // 8-bit alpha mask
+    Mask,
+    // 1-bit icon and mask
+    Bitmap,
+    // 4-bit or 8-bit with the system palette
+    Indexed,
+    // not an image, or data not matching the type
+    Other,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct IcnsEntry {
+    pub os_type: String,
+    // absolute offset and length of the entry data, without the 8-byte header
+    pub offset: u64,
+    pub length: u64,
+    // 2 for retina (@2x) variants
+    pub scale: u8,
+    pub payload: IcnsPayload,
+    // pixel size, None for non-image entries
+    pub size: Option<ImageSize>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct IcnsDetails {
+    // entries in file order, including non-image ones
+    pub entries: Vec<IcnsEntry>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Jpeg2000Component {
+    pub bit_depth: u8,
+    pub signed: bool,
+    // subsampling on the reference grid
+    pub horizontal_separation: u8,
+    pub vertical_separation: u8,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Jpeg2000StandardFeature {
+    pub id: u16,
+    pub mask: u64,
+}
+
+// JPX reader requirements (rreq) box
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Jpeg2000ReaderRequirements {
+    pub fully_understand_mask: u64,
+    pub decode_completely_mask: u64,
+    pub standard_features: Vec<Jpeg2000StandardFeature>,
+    // UUIDs as hexadecimal strings
+    pub vendor_features: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Jpeg2000Details {
+    // SIZ Rsiz
+    pub capabilities: u16,
+    // High Throughput (Part 15) block coder
+    pub is_htj2k: bool,
+    // image area, without the offset
+    pub size: ImageSize,
+    // XOsiz/YOsiz
+    pub image_offset: (u32, u32),
+    pub tile_size: ImageSize,
+    // XTOsiz/YTOsiz
+    pub tile_offset: (u32, u32),
+    pub tile_columns: u32,
+    pub tile_rows: u32,
+    pub components: Vec<Jpeg2000Component>,
+    // default COD values
+    pub decomposition_levels: u8,
+    pub reversible: bool,
+    pub layers: u16,
+    // 0 LRCP, 1 RLCP, 2 RPCL, 3 PCRL, 4 CPRL
+    pub progression_order: u8,
+    // full size first, then each reduced resolution down to the lowest
+    pub resolutions: Vec<ImageSize>,
+    // None for raw codestreams and files without rreq
+    pub reader_requirements: Option<Jpeg2000ReaderRequirements>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum JpegCodingProcess {
+    Baseline,
+    ExtendedSequential,
+    Progressive,
+    Lossless,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct JpegComponent {
+    pub id: u8,
+    pub horizontal_sampling: u8,
+    pub vertical_sampling: u8,
+    pub quantization_table: u8,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct JpegQuantizationTable {
+    pub id: u8,
+    // 0 for 8-bit, 1 for 16-bit values
+    pub precision: u8,
+    // 64 values in natural (row-major) order
+    pub values: Vec<u16>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct JpegDetails {
+    // SOFn marker of the first frame, 0xC0 to 0xCF
+    pub sof_marker: u8,
+    pub coding_process: JpegCodingProcess,
+    // arithmetic instead of Huffman coding
+    pub arithmetic_coding: bool,
+    // frame of a hierarchical image
+    pub differential: bool,
+    pub precision: u8,
+    pub components: Vec<JpegComponent>,
+    // DRI interval, None if no restart markers are used
+    pub restart_interval: Option<u16>,
+    // JFIF APP0 version, e.g. "1.01"
+    pub jfif_version: Option<String>,
+    // Adobe APP14 transform: 0 none (RGB or CMYK), 1 YCbCr, 2 YCCK
+    pub adobe_transform: Option<u8>,
+    // DQT tables defined before the first scan
+    pub quantization_tables: Vec<JpegQuantizationTable>,
+    // An estimate only: the libjpeg quality (1-100) whose standard tables are
+    // the closest to the ones in the file, None for lossless images or
+    // without tables. Encoders with custom tables map to a nearby value.
+    pub estimated_quality: Option<u8>,
+}
+
+impl JpegDetails {
+    // J:a:b notation of the chroma subsampling, None if the chroma
+    // components do not share the same factors
+    pub fn subsampling(&self) -> Option<&'static str> {
+        let luma = self.components.first()?;
+        if self.components.len() == 1 {
+            return Some("4:0:0");
+        }
+        let chroma = &self.components[1];
+        if self.components[1..self.components.len().min(3)]
+            .iter()
+            .any(|c| {
+                c.horizontal_sampling != chroma.horizontal_sampling
+                    || c.vertical_sampling != chroma.vertical_sampling
+            })
+            || chroma.horizontal_sampling == 0
+            || chroma.vertical_sampling == 0
+            || luma.horizontal_sampling % chroma.horizontal_sampling != 0
+            || luma.vertical_sampling % chroma.vertical_sampling != 0
+        {
+            return None;
+        }
+        match (
+            luma.horizontal_sampling / chroma.horizontal_sampling,
+            luma.vertical_sampling / chroma.vertical_sampling,
+        ) {
+            (1, 1) => Some("4:4:4"),
+            (2, 1) => Some("4:2:2"),
+            (2, 2) => Some("4:2:0"),
+            (1, 2) => Some("4:4:0"),
+            (4, 1) => Some("4:1:1"),
+            (4, 2) => Some("4:1:0"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PngChunk {
+    pub chunk_type: String,
+    // absolute offset of the chunk (length field) and size of its data
+    pub offset: u64,
+    pub length: u32,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PngChromaticities {
+    // CIE (x, y) coordinates
+    pub white_point: (f64, f64),
+    pub red: (f64, f64),
+    pub green: (f64, f64),
+    pub blue: (f64, f64),
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PngTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PngText {
+    // tEXt, zTXt or iTXt
+    pub chunk_type: String,
+    pub keyword: String,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PngDetails {
+    pub bit_depth: u8,
+    pub color_type: u8,
+    // 0 none, 1 Adam7
+    pub interlace_method: u8,
+    // chunks in file order, up to IEND or the first truncated chunk
+    pub chunks: Vec<PngChunk>,
+    pub gamma: Option<f64>,
+    pub chromaticities: Option<PngChromaticities>,
+    // sBIT, one value per channel
+    pub significant_bits: Option<Vec<u8>>,
+    pub texts: Vec<PngText>,
+    pub last_modified: Option<PngTime>,
+    // tRNS present
+    pub has_transparency: bool,
+    // false if the file ends before the IEND chunk
+    pub has_iend: bool,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum WebpCompression {
+    // VP8
+    Lossy,
+    // VP8L
+    Lossless,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct WebpChunk {
+    pub chunk_type: String,
+    // absolute offset of the chunk header and size of its data, without padding
+    pub offset: u64,
+    pub length: u32,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct WebpDetails {
+    // size from the RIFF header, counted from offset 8
+    pub riff_size: u32,
+    // the file ends before the RIFF size or inside a chunk
+    pub truncated: bool,
+    // bitstream of the image, or of the first frame of an animation
+    pub compression: Option<WebpCompression>,
+    // VP8X feature flags as written, None for simple (non-extended) files
+    pub vp8x_flags: Option<u8>,
+    // features found in the chunks, whatever the flags claim
+    pub has_alpha: bool,
+    pub has_icc: bool,
+    pub has_exif: bool,
+    pub has_xmp: bool,
+    pub is_animated: bool,
+    // top-level chunks in file order
+    pub chunks: Vec<WebpChunk>,
+}
+
+// Format specific structure beyond what ImageInfo reports
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum ImageDetails {
+    Bmp(BmpDetails),
+    Gif(GifDetails),
+    Heif(HeifDetails),
+    Icns(IcnsDetails),
+    // ICO and CUR
+    Ico(IcoDetails),
+    // J2K, JP2, JPH and JPX
+    Jpeg2000(Jpeg2000Details),
+    Jpeg(JpegDetails),
+    Png(PngDetails),
+    Psd(PsdDetails),
+    Qoi(QoiDetails),
+    Tga(TgaDetails),
+    Tiff(TiffDetails),
+    Webp(WebpDetails),
+}
+
+// Details of data already detected as format
+pub(crate) fn read_details<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    format: ImageFormat,
+) -> ImageInfoResult<Option<ImageDetails>>
+where
+    R: BufRead + Seek,
+{
+    match format {
+        ImageFormat::AVIF | ImageFormat::HEIC => {
+            let meta = read_heif_meta(ri, length)?;
+            Ok(Some(ImageDetails::Heif(read_heif_details(ri, &meta)?)))
+        }
+        ImageFormat::BMP => Ok(Some(ImageDetails::Bmp(read_bmp_details(ri, length)?))),
+        ImageFormat::GIF => Ok(Some(ImageDetails::Gif(read_gif_details(ri, length)?))),
+        ImageFormat::ICNS => Ok(Some(ImageDetails::Icns(IcnsDetails {
+            entries: read_icns_entries(ri, length)?,
+        }))),
+        ImageFormat::ICO | ImageFormat::CUR => Ok(Some(ImageDetails::Ico(IcoDetails {
+            entries: read_ico_entries(ri, length, format == ImageFormat::CUR)?,
+        }))),
+        ImageFormat::J2K | ImageFormat::JP2 | ImageFormat::JPH | ImageFormat::JPX => Ok(Some(
+            ImageDetails::Jpeg2000(read_jpeg2000_details(ri, length)?),
+        )),
+        ImageFormat::JPEG => Ok(Some(ImageDetails::Jpeg(read_jpeg_details(ri, length)?))),
+        ImageFormat::PNG => Ok(Some(ImageDetails::Png(read_png_details(ri, length)?))),
+        ImageFormat::PSD => Ok(Some(ImageDetails::Psd(read_psd_details(ri, length)?))),
+        ImageFormat::QOI => Ok(Some(ImageDetails::Qoi(read_qoi_details(ri, length)?))),
+        ImageFormat::TGA => Ok(Some(ImageDetails::Tga(read_tga_details(ri, length)?))),
+        ImageFormat::TIFF => Ok(Some(ImageDetails::Tiff(TiffDetails {
+            pages: read_tiff_pages(ri, length)?,
+        }))),
+        ImageFormat::WEBP => Ok(Some(ImageDetails::Webp(read_webp_details(ri, length)?))),
+        _ => Ok(None),
+    }
+}
+
+impl ImageDetails {
+    // Ok(None) if no details are available for the detected format
+    pub fn from_reader<R>(reader: &mut R) -> ImageInfoResult<Option<ImageDetails>>
+    where
+        R: BufRead + Seek,
+    {
+        let length = reader.seek(SeekFrom::End(0))? as usize;
+        let mut ri = ReadInterface::from_reader(reader, length);
+        let info = detect(&mut ri, length)?;
+
+        read_details(&mut ri, length, info.format)
+    }
+
+    pub fn from_file(file: &File) -> ImageInfoResult<Option<ImageDetails>> {
+        let mut reader = BufReader::new(file);
+        Self::from_reader(&mut reader)
+    }
+
+    pub fn from_file_path(filepath: impl AsRef<Path>) -> ImageInfoResult<Option<ImageDetails>> {
+        let file = File::open(filepath)?;
+        Self::from_file(&file)
+    }
+
+    pub fn from_raw_data(data: &[u8]) -> ImageInfoResult<Option<ImageDetails>> {
+        let mut reader = BufReader::new(Cursor::new(data));
+        Self::from_reader(&mut reader)
+    }
+}
@@ -0,0 +1,407 @@
+use crate::heif::read_heif_meta;
+use crate::ifd::{Ifd, IfdReader};
+use crate::{detect, ImageFormat, ImageInfoError, ImageInfoResult, ReadInterface};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Seek, SeekFrom};
+use std::path::Path;
+
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+const TAG_EXPOSURE_TIME: u16 = 0x829A;
+const TAG_F_NUMBER: u16 = 0x829D;
+const TAG_ISO: u16 = 0x8827;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_LENS_MODEL: u16 = 0xA434;
+const TAG_GPS_LATITUDE_REF: u16 = 0x0001;
+const TAG_GPS_LATITUDE: u16 = 0x0002;
+const TAG_GPS_LONGITUDE_REF: u16 = 0x0003;
+const TAG_GPS_LONGITUDE: u16 = 0x0004;
+const TAG_GPS_ALTITUDE_REF: u16 = 0x0005;
+const TAG_GPS_ALTITUDE: u16 = 0x0006;
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Rational {
+    pub numerator: u32,
+    pub denominator: u32,
+}
+
+impl Rational {
+    pub fn to_f64(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+}
+
+impl std::fmt::Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}/{}", self.numerator, self.denominator))
+    }
+}
+
+// Decimal degrees, negative for south latitudes and west longitudes,
+// altitude in meters, negative below sea level.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct GpsCoordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Exif {
+    pub orientation: Option<u16>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub date_time_original: Option<String>,
+    pub exposure_time: Option<Rational>,
+    pub f_number: Option<Rational>,
+    pub iso: Option<u32>,
+    pub lens_model: Option<String>,
+    pub gps: Option<GpsCoordinates>,
+}
+
+impl Exif {
+    // Ok(None) if the image carries no readable EXIF block
+    pub fn from_reader<R>(reader: &mut R) -> ImageInfoResult<Option<Exif>>
+    where
+        R: BufRead + Seek,
+    {
+        let length = reader.seek(SeekFrom::End(0))? as usize;
+        let mut ri = ReadInterface::from_reader(reader, length);
+        let info = detect(&mut ri, length)?;
+
+        let exif = find_exif(&mut ri, length, &info.format).and_then(|range| match range {
+            Some((base, end)) => read_exif(&mut ri, base, end).map(Some),
+            None => Ok(None),
+        });
+        match exif {
+            Err(ImageInfoError::UnrecognizedFormat) => Ok(None),
+            exif => exif,
+        }
+    }
+
+    pub fn from_file(file: &File) -> ImageInfoResult<Option<Exif>> {
+        let mut reader = BufReader::new(file);
+        Self::from_reader(&mut reader)
+    }
+
+    pub fn from_file_path(filepath: impl AsRef<Path>) -> ImageInfoResult<Option<Exif>> {
+        let file = File::open(filepath)?;
+        Self::from_file(&file)
+    }
+
+    pub fn from_raw_data(data: &[u8]) -> ImageInfoResult<Option<Exif>> {
+        let mut reader = BufReader::new(Cursor::new(data));
+        Self::from_reader(&mut reader)
+    }
+}
+
+fn read_rational<R>(
+    ri: &mut ReadInterface<R>,
+    reader: &IfdReader,
+    ifd: &Ifd,
+    tag: u16,
+    index: u64,
+) -> ImageInfoResult<Option<Rational>>
+where
+    R: BufRead + Seek,
+{
+    if let Some(entry) = ifd.find(tag) {
+        if let Some((numerator, denominator)) = reader.read_rational(ri, entry, index)? {
+            return Ok(Some(Rational {
+                numerator,
+                denominator,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+fn read_ascii<R>(
+    ri: &mut ReadInterface<R>,
+    reader: &IfdReader,
+    ifd: &Ifd,
+    tag: u16,
+) -> ImageInfoResult<Option<String>>
+where
+    R: BufRead + Seek,
+{
+    match ifd.find(tag) {
+        Some(entry) => reader.read_ascii(ri, entry),
+        None => Ok(None),
+    }
+}
+
+fn read_uint<R>(
+    ri: &mut ReadInterface<R>,
+    reader: &IfdReader,
+    ifd: &Ifd,
+    tag: u16,
+) -> ImageInfoResult<Option<u64>>
+where
+    R: BufRead + Seek,
+{
+    match ifd.find(tag) {
+        Some(entry) => reader.read_uint(ri, entry, 0),
+        None => Ok(None),
+    }
+}
+
+// Sub IFDs are optional, a broken pointer only drops the tags it holds
+fn read_sub_ifd<R>(
+    ri: &mut ReadInterface<R>,
+    reader: &IfdReader,
+    ifd: &Ifd,
+    tag: u16,
+) -> ImageInfoResult<Option<Ifd>>
+where
+    R: BufRead + Seek,
+{
+    match read_uint(ri, reader, ifd, tag)? {
+        Some(offset) => match reader.read_ifd(ri, offset) {
+            Ok(sub_ifd) => Ok(Some(sub_ifd)),
+            Err(ImageInfoError::UnrecognizedFormat) => Ok(None),
+            Err(err) => Err(err),
+        },
+        None => Ok(None),
+    }
+}
+
+fn read_gps_degrees<R>(
+    ri: &mut ReadInterface<R>,
+    reader: &IfdReader,
+    ifd: &Ifd,
+    tag: u16,
+) -> ImageInfoResult<Option<f64>>
+where
+    R: BufRead + Seek,
+{
+    let mut degrees = 0f64;
+    for (i, unit) in [1f64, 60f64, 3600f64].iter().enumerate() {
+        match read_rational(ri, reader, ifd, tag, i as u64)? {
+            Some(value) if value.denominator != 0 => degrees += value.to_f64() / unit,
+            _ => return Ok(None),
+        }
+    }
+    Ok(Some(degrees))
+}
+
+fn read_gps<R>(
+    ri: &mut ReadInterface<R>,
+    reader: &IfdReader,
+    ifd: &Ifd,
+) -> ImageInfoResult<Option<GpsCoordinates>>
+where
+    R: BufRead + Seek,
+{
+    let latitude = read_gps_degrees(ri, reader, ifd, TAG_GPS_LATITUDE)?;
+    let longitude = read_gps_degrees(ri, reader, ifd, TAG_GPS_LONGITUDE)?;
+    let (mut latitude, mut longitude) = match (latitude, longitude) {
+        (Some(latitude), Some(longitude)) => (latitude, longitude),
+        _ => return Ok(None),
+    };
+    if read_ascii(ri, reader, ifd, TAG_GPS_LATITUDE_REF)?.as_deref() == Some("S") {
+        latitude = -latitude;
+    }
+    if read_ascii(ri, reader, ifd, TAG_GPS_LONGITUDE_REF)?.as_deref() == Some("W") {
+        longitude = -longitude;
+    }
+    let altitude = match read_rational(ri, reader, ifd, TAG_GPS_ALTITUDE, 0)? {
+        Some(value) if value.denominator != 0 => {
+            // 1 means below sea level
+            if read_uint(ri, reader, ifd, TAG_GPS_ALTITUDE_REF)? == Some(1) {
+                Some(-value.to_f64())
+            } else {
+                Some(value.to_f64())
+            }
+        }
+        _ => None,
+    };
+    Ok(Some(GpsCoordinates {
+        latitude,
+        longitude,
+        altitude,
+    }))
+}
+
+// base and end are the absolute range of the tiff structure
+// https://www.cipa.jp/std/documents/e/DC-008-2012_E.pdf
+pub fn read_exif<R>(ri: &mut ReadInterface<R>, base: usize, end: usize) -> ImageInfoResult<Exif>
+where
+    R: BufRead + Seek,
+{
+    let reader = IfdReader::new(ri, base, end)?;
+    let ifd0 = reader.read_ifd(ri, reader.first_ifd)?;
+
+    let mut exif = Exif {
+        orientation: read_uint(ri, &reader, &ifd0, TAG_ORIENTATION)?.map(|v| v as u16),
+        make: read_ascii(ri, &reader, &ifd0, TAG_MAKE)?,
+        model: read_ascii(ri, &reader, &ifd0, TAG_MODEL)?,
+        ..Default::default()
+    };
+
+    if let Some(ifd) = read_sub_ifd(ri, &reader, &ifd0, TAG_EXIF_IFD)? {
+        exif.date_time_original = read_ascii(ri, &reader, &ifd, TAG_DATE_TIME_ORIGINAL)?;
+        exif.exposure_time = read_rational(ri, &reader, &ifd, TAG_EXPOSURE_TIME, 0)?;
+        exif.f_number = read_rational(ri, &reader, &ifd, TAG_F_NUMBER, 0)?;
+        exif.iso = read_uint(ri, &reader, &ifd, TAG_ISO)?.map(|v| v as u32);
+        exif.lens_model = read_ascii(ri, &reader, &ifd, TAG_LENS_MODEL)?;
+    }
+
+    if let Some(ifd) = read_sub_ifd(ri, &reader, &ifd0, TAG_GPS_IFD)? {
+        exif.gps = read_gps(ri, &reader, &ifd)?;
+    }
+
+    Ok(exif)
+}
+
+// Only reads the first IFD, cheap enough for size detection
+pub fn read_orientation<R>(
+    ri: &mut ReadInterface<R>,
+    base: usize,
+    end: usize,
+) -> ImageInfoResult<Option<u16>>
+where
+    R: BufRead + Seek,
+{
+    let reader = IfdReader::new(ri, base, end)?;
+    let ifd0 = reader.read_ifd(ri, reader.first_ifd)?;
+    Ok(read_uint(ri, &reader, &ifd0, TAG_ORIENTATION)?.map(|v| v as u16))
+}
+
+// Absolute range of the tiff structure holding the EXIF tags
+pub fn find_exif<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    format: &ImageFormat,
+) -> ImageInfoResult<Option<(usize, usize)>>
+where
+    R: BufRead + Seek,
+{
+    match format {
+        ImageFormat::JPEG => find_exif_jpg(ri, length),
+        ImageFormat::TIFF => Ok(Some((0, length))),
+        ImageFormat::WEBP => find_exif_webp(ri, length),
+        ImageFormat::PNG => find_exif_png(ri, length),
+        ImageFormat::AVIF | ImageFormat::HEIC => find_exif_heif(ri, length),
+        _ => Ok(None),
+    }
+}
+
+// APP1 segment starting with "Exif\0\0"
+fn find_exif_jpg<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+) -> ImageInfoResult<Option<(usize, usize)>>
+where
+    R: BufRead + Seek,
+{
+    let mut offset = 2usize;
+    while offset + 4 <= length {
+        let buffer = ri.read(offset, 4)?;
+        if !buffer.cmp(0, 1, b"\xFF") {
+            // skip garbage bytes
+            offset += 1;
+            continue;
+        }
+        // Start of scan, no more metadata
+        if buffer.cmp_any_of(0, 2, vec![b"\xFF\xDA", b"\xFF\xD9"]) {
+            break;
+        }
+        let section_size = buffer.read_u16_be(2) as usize;
+        if buffer.cmp(0, 2, b"\xFF\xE1") && section_size >= 8 && offset + section_size + 2 <= length
+        {
+            let buffer = ri.read(offset + 4, 6)?;
+            if buffer.cmp(0, 6, b"Exif\x00\x00") {
+                return Ok(Some((offset + 10, offset + section_size + 2)));
+            }
+        }
+        offset += section_size + 2;
+    }
+    Ok(None)
+}
+
+// eXIf chunk
+fn find_exif_png<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+) -> ImageInfoResult<Option<(usize, usize)>>
+where
+    R: BufRead + Seek,
+{
+    let mut offset = 8usize;
+    while offset + 12 <= length {
+        let buffer = ri.read(offset, 8)?;
+        let chunk_size = buffer.read_u32_be(0) as usize;
+        if offset + 12 + chunk_size > length {
+            break;
+        }
+        if buffer.cmp(4, 4, b"eXIf") {
+            return Ok(Some((offset + 8, offset + 8 + chunk_size)));
+        }
+        if buffer.cmp(4, 4, b"IEND") {
+            break;
+        }
+        offset += 12 + chunk_size;
+    }
+    Ok(None)
+}
+
+// EXIF chunk, some writers keep the "Exif\0\0" prefix of jpeg
+fn find_exif_webp<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+) -> ImageInfoResult<Option<(usize, usize)>>
+where
+    R: BufRead + Seek,
+{
+    let mut offset = 12usize;
+    while offset + 8 <= length {
+        let buffer = ri.read(offset, 8)?;
+        let chunk_size = buffer.read_u32_le(4) as usize;
+        if offset + 8 + chunk_size > length {
+            break;
+        }
+        if buffer.cmp(0, 4, b"EXIF") {
+            let mut start = offset + 8;
+            let end = start + chunk_size;
+            if chunk_size >= 6 && ri.read(start, 6)?.cmp(0, 6, b"Exif\x00\x00") {
+                start += 6;
+            }
+            return Ok(Some((start, end)));
+        }
+        offset += 8 + chunk_size + (chunk_size & 1);
+    }
+    Ok(None)
+}
+
+// Exif item, its data starts with the offset of the tiff header
+fn find_exif_heif<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+) -> ImageInfoResult<Option<(usize, usize)>>
+where
+    R: BufRead + Seek,
+{
+    let meta = read_heif_meta(ri, length)?;
+    let (offset, size) = match meta.find_item("Exif").and_then(|item| item.extents.first()) {
+        Some(&extent) => extent,
+        None => return Ok(None),
+    };
+    if offset.saturating_add(size) > length as u64 || size < 4 {
+        return Ok(None);
+    }
+    let (offset, end) = (offset as usize, (offset + size) as usize);
+    let header_offset = ri.read(offset, 4)?.read_u32_be(0) as usize;
+    if header_offset > end - offset - 4 {
+        return Ok(None);
+    }
+    Ok(Some((offset + 4 + header_offset, end)))
+}
@@ -0,0 +1,66 @@
+use crate::{ImageFormat, ImageInfo, ImageInfoResult};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Seek};
+use std::path::Path;
+
+// Whether the extension of a file name agrees with the detected format
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ExtensionCheck {
+    // as written in the file name, without the dot, None if there is none
+    pub extension: Option<String>,
+    pub format: ImageFormat,
+    // the extension is one of the format's, case insensitive
+    pub matches: bool,
+    // what the detector reports, e.g. "jpg" or "psb"
+    pub suggested_extension: &'static str,
+}
+
+impl ExtensionCheck {
+    fn new(file_name: &Path, info: ImageInfo) -> ExtensionCheck {
+        let extension = file_name
+            .extension()
+            .map(|ext| ext.to_string_lossy().into_owned());
+        let matches = match &extension {
+            Some(ext) => ImageFormat::from_extension(ext) == Some(info.format),
+            None => false,
+        };
+        ExtensionCheck {
+            extension,
+            format: info.format,
+            matches,
+            suggested_extension: info.ext,
+        }
+    }
+
+    pub fn from_reader<R>(
+        file_name: impl AsRef<Path>,
+        reader: &mut R,
+    ) -> ImageInfoResult<ExtensionCheck>
+    where
+        R: BufRead + Seek,
+    {
+        let info = ImageInfo::from_reader(reader)?;
+        Ok(Self::new(file_name.as_ref(), info))
+    }
+
+    pub fn from_file(file_name: impl AsRef<Path>, file: &File) -> ImageInfoResult<ExtensionCheck> {
+        let mut reader = BufReader::new(file);
+        Self::from_reader(file_name, &mut reader)
+    }
+
+    pub fn from_file_path(filepath: impl AsRef<Path>) -> ImageInfoResult<ExtensionCheck> {
+        let file = File::open(filepath.as_ref())?;
+        Self::from_file(filepath, &file)
+    }
+
+    pub fn from_raw_data(
+        file_name: impl AsRef<Path>,
+        data: &[u8],
+    ) -> ImageInfoResult<ExtensionCheck> {
+        let mut reader = BufReader::new(Cursor::new(data));
+        Self::from_reader(file_name, &mut reader)
+    }
+}
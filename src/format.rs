@@ -0,0 +1,312 @@
+use crate::{ImageFormat, ImageInfo, ImageInfoError, ImageSize};
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+use std::str::FromStr;
+
+struct FormatEntry {
+    // the variant name, as written by Display
+    code: &'static str,
+    name: &'static str,
+    // reported by ImageInfo along with the first extension
+    full_ext: &'static str,
+    // the first one is the preferred extension
+    extensions: &'static [&'static str],
+    // the first one is reported by ImageInfo, the rest are aliases seen in the
+    // wild, including the IANA registered type where it differs
+    mime_types: &'static [&'static str],
+}
+
+// Iteration order, the same as the enum
+const FORMATS: [ImageFormat; 21] = [
+    ImageFormat::AVIF,
+    ImageFormat::HEIC,
+    ImageFormat::BMP,
+    ImageFormat::CUR,
+    ImageFormat::ICO,
+    ImageFormat::DDS,
+    ImageFormat::GIF,
+    ImageFormat::HDR,
+    ImageFormat::ICNS,
+    ImageFormat::J2K,
+    ImageFormat::JP2,
+    ImageFormat::JPH,
+    ImageFormat::JPX,
+    ImageFormat::JPEG,
+    ImageFormat::KTX,
+    ImageFormat::PNG,
+    ImageFormat::PSD,
+    ImageFormat::QOI,
+    ImageFormat::TIFF,
+    ImageFormat::WEBP,
+    ImageFormat::TGA,
+];
+
+impl ImageFormat {
+    fn entry(&self) -> FormatEntry {
+        match self {
+            ImageFormat::AVIF => FormatEntry {
+                code: "AVIF",
+                name: "AV1 Image File Format",
+                full_ext: "avif",
+                extensions: &["avif", "avifs"],
+                mime_types: &["image/avif", "image/avif-sequence"],
+            },
+            ImageFormat::HEIC => FormatEntry {
+                code: "HEIC",
+                name: "High Efficiency Image File Format",
+                full_ext: "heic",
+                extensions: &["heic", "heif", "heics", "heifs", "hif"],
+                mime_types: &[
+                    "image/heic",
+                    "image/heif",
+                    "image/heic-sequence",
+                    "image/heif-sequence",
+                ],
+            },
+            ImageFormat::BMP => FormatEntry {
+                code: "BMP",
+                name: "Windows Bitmap",
+                full_ext: "bmp",
+                extensions: &["bmp", "dib"],
+                mime_types: &["image/bmp", "image/x-bmp", "image/x-ms-bmp"],
+            },
+            ImageFormat::CUR => FormatEntry {
+                code: "CUR",
+                name: "Windows Cursor",
+                full_ext: "cur",
+                extensions: &["cur"],
+                mime_types: &["image/cur", "image/x-win-bitmap"],
+            },
+            ImageFormat::ICO => FormatEntry {
+                code: "ICO",
+                name: "Windows Icon",
+                full_ext: "ico",
+                extensions: &["ico"],
+                mime_types: &["image/ico", "image/vnd.microsoft.icon", "image/x-icon"],
+            },
+            ImageFormat::DDS => FormatEntry {
+                code: "DDS",
+                name: "DirectDraw Surface",
+                full_ext: "dds",
+                extensions: &["dds"],
+                mime_types: &["image/dds", "image/vnd-ms.dds", "image/x-dds"],
+            },
+            ImageFormat::GIF => FormatEntry {
+                code: "GIF",
+                name: "Graphics Interchange Format",
+                full_ext: "gif",
+                extensions: &["gif"],
+                mime_types: &["image/gif"],
+            },
+            ImageFormat::HDR => FormatEntry {
+                code: "HDR",
+                name: "Radiance HDR",
+                full_ext: "hdr",
+                extensions: &["hdr", "pic", "rgbe"],
+                mime_types: &["image/vnd.radiance", "image/x-hdr"],
+            },
+            ImageFormat::ICNS => FormatEntry {
+                code: "ICNS",
+                name: "Apple Icon Image",
+                full_ext: "icns",
+                extensions: &["icns"],
+                mime_types: &["image/icns", "image/x-icns"],
+            },
+            ImageFormat::J2K => FormatEntry {
+                code: "J2K",
+                name: "JPEG 2000 Code Stream",
+                full_ext: "j2k",
+                extensions: &["j2k", "j2c", "jpc", "jhc"],
+                mime_types: &["image/j2k", "image/j2c", "image/x-jp2-codestream"],
+            },
+            ImageFormat::JP2 => FormatEntry {
+                code: "JP2",
+                name: "JPEG 2000",
+                full_ext: "jp2",
+                extensions: &["jp2"],
+                mime_types: &["image/jp2"],
+            },
+            ImageFormat::JPH => FormatEntry {
+                code: "JPH",
+                name: "High Throughput JPEG 2000",
+                full_ext: "jph",
+                extensions: &["jph"],
+                mime_types: &["image/jph"],
+            },
+            ImageFormat::JPX => FormatEntry {
+                code: "JPX",
+                name: "JPEG 2000 Extended",
+                full_ext: "jpx",
+                extensions: &["jpx", "jpf"],
+                mime_types: &["image/jpx"],
+            },
+            ImageFormat::JPEG => FormatEntry {
+                code: "JPEG",
+                name: "JPEG",
+                full_ext: "jpeg",
+                extensions: &["jpg", "jpeg", "jpe", "jfif", "jif"],
+                mime_types: &["image/jpeg", "image/jpg", "image/pjpeg"],
+            },
+            ImageFormat::KTX => FormatEntry {
+                code: "KTX",
+                name: "Khronos Texture",
+                full_ext: "ktx",
+                extensions: &["ktx"],
+                mime_types: &["image/ktx"],
+            },
+            ImageFormat::PNG => FormatEntry {
+                code: "PNG",
+                name: "Portable Network Graphics",
+                full_ext: "png",
+                extensions: &["png", "apng"],
+                mime_types: &["image/png", "image/apng", "image/x-png"],
+            },
+            ImageFormat::PSD => FormatEntry {
+                code: "PSD",
+                name: "Adobe Photoshop Document",
+                full_ext: "psd",
+                extensions: &["psd", "psb"],
+                mime_types: &[
+                    "image/psd",
+                    "image/vnd.adobe.photoshop",
+                    "image/x-photoshop",
+                    "application/x-photoshop",
+                ],
+            },
+            ImageFormat::QOI => FormatEntry {
+                code: "QOI",
+                name: "Quite OK Image",
+                full_ext: "qoi",
+                extensions: &["qoi"],
+                mime_types: &["image/qoi", "image/x-qoi"],
+            },
+            ImageFormat::TIFF => FormatEntry {
+                code: "TIFF",
+                name: "Tagged Image File Format",
+                full_ext: "tiff",
+                extensions: &["tif", "tiff"],
+                mime_types: &["image/tiff", "image/x-tiff"],
+            },
+            ImageFormat::WEBP => FormatEntry {
+                code: "WEBP",
+                name: "WebP",
+                full_ext: "webp",
+                extensions: &["webp"],
+                mime_types: &["image/webp"],
+            },
+            ImageFormat::TGA => FormatEntry {
+                code: "TGA",
+                name: "Truevision TGA",
+                full_ext: "tga",
+                extensions: &["tga", "icb", "vda", "vst", "tpic"],
+                mime_types: &["image/tga", "image/x-tga", "image/x-targa"],
+            },
+        }
+    }
+
+    pub fn iter() -> impl Iterator<Item = ImageFormat> {
+        FORMATS.iter().copied()
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.entry().name
+    }
+
+    pub fn extensions(&self) -> &'static [&'static str] {
+        self.entry().extensions
+    }
+
+    // what ImageInfo reports
+    pub fn mime_type(&self) -> &'static str {
+        self.entry().mime_types[0]
+    }
+
+    // reported type first
+    pub fn mime_types(&self) -> &'static [&'static str] {
+        self.entry().mime_types
+    }
+
+    // case insensitive, with or without the leading dot
+    pub fn from_extension(ext: &str) -> Option<ImageFormat> {
+        let ext = ext.strip_prefix('.').unwrap_or(ext).to_ascii_lowercase();
+        Self::iter().find(|format| format.extensions().contains(&ext.as_str()))
+    }
+
+    // case insensitive, parameters such as "; charset=binary" are ignored
+    pub fn from_mime(mime: &str) -> Option<ImageFormat> {
+        let mime = mime.split(';').next().unwrap_or("").trim();
+        let mime = mime.to_ascii_lowercase();
+        Self::iter().find(|format| format.mime_types().contains(&mime.as_str()))
+    }
+}
+
+impl ImageInfo {
+    // What a detector reports, the strings come from the registry
+    pub(crate) fn from_format(format: ImageFormat, size: ImageSize) -> ImageInfo {
+        let entry = format.entry();
+        ImageInfo {
+            format,
+            ext: entry.extensions[0],
+            full_ext: entry.full_ext,
+            mimetype: entry.mime_types[0],
+            size,
+            entry_sizes: vec![],
+        }
+    }
+}
+
+impl std::fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.pad(self.entry().code)
+    }
+}
+
+// The variant name as written by Display, or any known extension
+impl FromStr for ImageFormat {
+    type Err = ImageInfoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::iter()
+            .find(|format| format.to_string().eq_ignore_ascii_case(s))
+            .or_else(|| Self::from_extension(s))
+            .ok_or(ImageInfoError::UnrecognizedFormat)
+    }
+}
+
+// ImageInfo as found in JSON, before mapping the strings to the registry
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct ImageInfoFields {
+    format: ImageFormat,
+    ext: String,
+    full_ext: String,
+    mimetype: String,
+    size: ImageSize,
+    entry_sizes: Vec<ImageSize>,
+}
+
+// ext, full_ext and mimetype are read back into the strings of the registry,
+// unknown ones are rejected
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for ImageInfo {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let fields = ImageInfoFields::deserialize(deserializer)?;
+        let format = fields.format;
+        let find = |known: &'static [&'static str], value: &str| {
+            known.iter().find(|k| **k == value).copied().ok_or_else(|| {
+                serde::de::Error::custom(format!("unknown {:?} for {}", value, format))
+            })
+        };
+        Ok(ImageInfo {
+            format,
+            ext: find(format.extensions(), &fields.ext)?,
+            full_ext: find(format.extensions(), &fields.full_ext)?,
+            mimetype: find(format.mime_types(), &fields.mimetype)?,
+            size: fields.size,
+            entry_sizes: fields.entry_sizes,
+        })
+    }
+}
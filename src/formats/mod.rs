@@ -16,20 +16,25 @@ mod try_tga;
 mod try_tiff;
 mod try_webp;
 
-pub use try_avif_heic::try_avif_heic;
-pub use try_bmp::try_bmp;
-pub use try_cur_ico::try_cur_ico;
-pub use try_dds::try_dds;
-pub use try_gif::try_gif;
-pub use try_hdr::try_hdr;
-pub use try_icns::try_icns;
-pub use try_jpeg2000::try_jpeg2000;
-pub use try_jpeg2000_code_stream::try_jpeg2000_code_stream;
-pub use try_jpg::try_jpg;
-pub use try_ktx::try_ktx;
-pub use try_png::try_png;
-pub use try_psd::try_psd;
-pub use try_qoi::try_qoi;
-pub use try_tga::try_tga;
-pub use try_tiff::try_tiff;
-pub use try_webp::try_webp;
+pub use try_avif_heic::{read_heif_details, try_avif_heic};
+pub use try_bmp::{bmp_header_version, read_bmp_details, try_bmp};
+pub use try_cur_ico::{read_ico_entries, try_cur_ico};
+pub use try_dds::{read_dds_mip_count, try_dds};
+pub use try_gif::{find_gif_end, read_gif_details, try_gif};
+pub use try_hdr::{parse_radiance_resolution, read_radiance_header, try_hdr};
+pub use try_icns::{read_icns_entries, try_icns};
+pub use try_jpeg2000::{read_jpeg2000_details, try_jpeg2000};
+pub use try_jpeg2000_code_stream::{read_jpeg2000_code_stream, try_jpeg2000_code_stream};
+pub use try_jpg::{find_jpeg_end, read_jpeg_details, try_jpg};
+pub use try_ktx::{read_ktx_levels, try_ktx};
+pub use try_png::{read_png_details, try_png};
+pub use try_psd::{read_psd_details, read_psd_resources, try_psd};
+pub use try_qoi::{read_qoi_details, try_qoi};
+pub use try_tga::{is_valid_tga_header, read_tga_details, try_tga};
+pub use try_tiff::{read_tiff_pages, try_tiff};
+pub use try_webp::{read_webp_details, try_webp};
+
+// Division rounding up, without overflowing near u64::MAX
+pub(crate) fn div_ceil(value: u64, divisor: u64) -> u64 {
+    value / divisor + (value % divisor != 0) as u64
+}
@@ -1,5 +1,7 @@
+use crate::details::{HeifAuxiliaryItem, HeifAuxiliaryKind, HeifDetails, HeifGrid, HeifImageItem};
+use crate::heif::{read_heif_meta, HeifMeta};
 use crate::{ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::io::{BufRead, Seek};
 
 // https://nokiatech.github.io/heif/technical.html
@@ -51,191 +53,133 @@ where
     let mut ret =
         // contains "avif"
         if compatible_brands.contains("avif") {
-            ImageInfo {
-                format: ImageFormat::AVIF,
-                ext: "avif",
-                full_ext: "avif",
-                mimetype: "image/avif",
-                size: ImageSize {
-                    width: 0,
-                    height: 0,
-                },
-                entry_sizes: vec![],
-            }
+            ImageInfo::from_format(ImageFormat::AVIF, ImageSize {
+                width: 0,
+                height: 0,
+            })
         }
         // contains "heic"
         else if compatible_brands.contains("heic") {
-            ImageInfo {
-                format: ImageFormat::HEIC,
-                ext: "heic",
-                full_ext: "heic",
-                mimetype: "image/heic",
-                size: ImageSize {
-                    width: 0,
-                    height: 0,
-                },
-                entry_sizes: vec![],
-            }
+            ImageInfo::from_format(ImageFormat::HEIC, ImageSize {
+                width: 0,
+                height: 0,
+            })
         }
         // Fall back to the major brand
         else if buffer.cmp(8, 4, b"avif") {
-            ImageInfo {
-                format: ImageFormat::AVIF,
-                ext: "avif",
-                full_ext: "avif",
-                mimetype: "image/avif",
-                size: ImageSize {
-                    width: 0,
-                    height: 0,
-                },
-                entry_sizes: vec![],
-            }
+            ImageInfo::from_format(ImageFormat::AVIF, ImageSize {
+                width: 0,
+                height: 0,
+            })
         }
         // Fall back to the major brand
         else if buffer.cmp(8, 4, b"heic") {
-            ImageInfo {
-                format: ImageFormat::HEIC,
-                ext: "heic",
-                full_ext: "heic",
-                mimetype: "image/heic",
-                size: ImageSize {
-                    width: 0,
-                    height: 0,
-                },
-                entry_sizes: vec![],
-            }
+            ImageInfo::from_format(ImageFormat::HEIC, ImageSize {
+                width: 0,
+                height: 0,
+            })
         }
         // invalid
         else {
             return Err(ImageInfoError::UnrecognizedFormat);
         };
 
-    if !buffer.cmp(ftyp_box_length + 4, 4, b"meta") {
-        return Err(ImageInfoError::UnrecognizedFormat);
+    let meta = read_heif_meta(ri, length)?;
+    match meta.item_size(ri, meta.primary_item)? {
+        Some(size) => {
+            ret.size = size;
+            Ok(ret)
+        }
+        None => Err(ImageInfoError::UnrecognizedFormat),
     }
+}
 
-    let meta_length = buffer.read_u32_be(ftyp_box_length) as usize;
-    if (length as u64) < (ftyp_box_length as u64) + 12u64 + (meta_length as u64) {
-        return Err(ImageInfoError::UnrecognizedFormat);
-    }
+// Auxiliary type URNs of alpha planes, depth maps and gain maps
+const ALPHA_AUX_TYPES: [&str; 3] = [
+    "urn:mpeg:mpegB:cicp:systems:auxiliary:alpha",
+    "urn:mpeg:hevc:2015:auxid:1",
+    "urn:mpeg:avc:2015:auxid:1",
+];
+const DEPTH_AUX_TYPES: [&str; 3] = [
+    "urn:mpeg:mpegB:cicp:systems:auxiliary:depth",
+    "urn:mpeg:hevc:2015:auxid:2",
+    "urn:mpeg:avc:2015:auxid:2",
+];
+const GAIN_MAP_AUX_TYPES: [&str; 1] = ["urn:com:apple:photo:2020:aux:hdrgainmap"];
 
-    let buffer = ri.read(ftyp_box_length + 12, meta_length)?;
-    let mut offset = 0usize;
-    let end = meta_length;
+fn read_item<R>(
+    ri: &mut ReadInterface<R>,
+    meta: &HeifMeta,
+    id: u32,
+) -> ImageInfoResult<HeifImageItem>
+where
+    R: BufRead + Seek,
+{
+    Ok(HeifImageItem {
+        id,
+        item_type: meta
+            .items
+            .iter()
+            .find(|item| item.id == id)
+            .map(|item| item.item_type.clone())
+            .unwrap_or_default(),
+        size: meta.item_size(ri, id)?,
+    })
+}
 
-    //
-    // find ispe box
-    //
-    // meta
-    //   - ...
-    //   - iprp
-    //       - ...
-    //       - ipco
-    //           - ...
-    //           - ispe
-    //
-    let mut pitm_id = 1;
-    let mut ipma_map: HashMap<u16, HashSet<u8>> = HashMap::new();
-    let mut ipco_start = 0usize;
-    let mut ipco_end = 0usize;
-    let mut ipco_child_index = 1;
-    let mut ispe_map: HashMap<u8, ImageSize> = HashMap::new();
-    let mut irot_map: HashMap<u8, u8> = HashMap::new();
-    while offset < end {
-        if offset + 8 > end {
-            break;
-        }
-        let box_size = buffer.read_u32_be(offset) as usize;
-        if box_size < 8 || (offset as u64) + (box_size as u64) > (end as u64) {
-            break;
-        }
+// Primary item with its grid/overlay inputs, auxiliary images (auxl) and
+// gain maps referenced by a tone map (tmap) item
+pub fn read_heif_details<R>(
+    ri: &mut ReadInterface<R>,
+    meta: &HeifMeta,
+) -> ImageInfoResult<HeifDetails>
+where
+    R: BufRead + Seek,
+{
+    let primary = meta.primary_item;
 
-        if buffer.cmp(offset + 4, 4, b"pitm") {
-            if box_size < 14 {
-                return Err(ImageInfoError::UnrecognizedFormat);
-            }
-            pitm_id = buffer.read_u16_be(offset + 12);
-            offset += box_size;
-        } else if buffer.cmp(offset + 4, 4, b"ipma") {
-            if box_size < 16 {
-                return Err(ImageInfoError::UnrecognizedFormat);
-            }
-            let entry_count = buffer.read_u16_be(offset + 14);
-            let mut t = offset + 16;
-            for _ in 0..entry_count {
-                if box_size < 18 {
-                    return Err(ImageInfoError::UnrecognizedFormat);
-                }
-                let item_id = buffer.read_u16_be(t);
-                t += 2;
-                if box_size < 19 {
-                    return Err(ImageInfoError::UnrecognizedFormat);
-                }
-                let index_count = buffer.read_u8(t);
-                t += 1;
-                if box_size < 19 + (index_count as usize) {
-                    return Err(ImageInfoError::UnrecognizedFormat);
-                }
-                let mut indices = HashSet::new();
-                for _ in 0..index_count {
-                    indices.insert(buffer.read_u8(t) & 0x0F);
-                    t += 1;
-                }
-                ipma_map.insert(item_id, indices);
-            }
-            offset += box_size;
-        } else if buffer.cmp(offset + 4, 4, b"iprp") {
-            offset += 8;
-        } else if buffer.cmp(offset + 4, 4, b"ipco") {
-            ipco_start = offset;
-            ipco_end = offset + box_size;
-            offset += 8;
-        } else if buffer.cmp(offset + 4, 4, b"ispe") {
-            if box_size < 20 {
-                return Err(ImageInfoError::UnrecognizedFormat);
-            }
-            let size = ImageSize {
-                width: buffer.read_u32_be(offset + 12) as i64,
-                height: buffer.read_u32_be(offset + 16) as i64,
-            };
-            ispe_map.insert(ipco_child_index, size);
-            ipco_child_index += 1;
-            offset += box_size;
-        } else if buffer.cmp(offset + 4, 4, b"irot") {
-            if box_size < 9 {
-                return Err(ImageInfoError::UnrecognizedFormat);
-            }
-            let irot = buffer.read_u8(offset + 8);
-            irot_map.insert(ipco_child_index, irot);
-            ipco_child_index += 1;
-            offset += box_size;
-        } else {
-            if offset > ipco_start && offset < ipco_end {
-                ipco_child_index += 1;
-            }
-            offset += box_size;
-        }
+    let grid = meta
+        .read_derived_image(ri, primary)?
+        .and_then(|derived| derived.grid)
+        .map(|(rows, columns)| HeifGrid { rows, columns });
+
+    let mut tiles = vec![];
+    for id in meta.referenced_items("dimg", primary) {
+        tiles.push(read_item(ri, meta, id)?);
     }
 
-    if let Some(indices) = ipma_map.get(&pitm_id) {
-        let mut irot = 0u8;
-        for it in irot_map {
-            if indices.contains(&(it.0)) {
-                irot = it.1;
-                break;
-            }
-        }
-        for it in ispe_map {
-            if indices.contains(&(it.0)) {
-                let mut size = it.1;
-                if irot == 1 || irot == 3 || irot == 6 || irot == 7 {
-                    std::mem::swap(&mut size.width, &mut size.height);
-                }
-                ret.size = size;
-                return Ok(ret);
+    let mut auxiliary_items = vec![];
+    for id in meta.referring_items("auxl", primary) {
+        let aux_type = meta.item_aux_type(id).map(|aux_type| aux_type.to_string());
+        let kind = match aux_type.as_deref() {
+            Some(t) if ALPHA_AUX_TYPES.contains(&t) => HeifAuxiliaryKind::Alpha,
+            Some(t) if DEPTH_AUX_TYPES.contains(&t) => HeifAuxiliaryKind::Depth,
+            Some(t) if GAIN_MAP_AUX_TYPES.contains(&t) => HeifAuxiliaryKind::GainMap,
+            _ => HeifAuxiliaryKind::Other,
+        };
+        auxiliary_items.push(HeifAuxiliaryItem {
+            item: read_item(ri, meta, id)?,
+            kind,
+            aux_type,
+        });
+    }
+    // ISO 21496-1: tmap refers to the base image first and the gain map second
+    for item in meta.items.iter().filter(|item| item.item_type == "tmap") {
+        if let [base, gain_map] = meta.referenced_items("dimg", item.id).as_slice() {
+            if *base == primary {
+                auxiliary_items.push(HeifAuxiliaryItem {
+                    item: read_item(ri, meta, *gain_map)?,
+                    kind: HeifAuxiliaryKind::GainMap,
+                    aux_type: None,
+                });
             }
         }
     }
 
-    Err(ImageInfoError::UnrecognizedFormat)
+    Ok(HeifDetails {
+        primary_item: read_item(ri, meta, primary)?,
+        grid,
+        tiles,
+        auxiliary_items,
+    })
 }
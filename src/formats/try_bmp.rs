@@ -1,6 +1,22 @@
+use crate::details::{BmpCompression, BmpDetails, BmpHeaderVersion, BmpIccProfile};
 use crate::{ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface};
+use std::cmp::min;
 use std::io::{BufRead, Seek};
 
+pub fn bmp_header_version(header_size: u32) -> Option<BmpHeaderVersion> {
+    match header_size {
+        12 => Some(BmpHeaderVersion::Core),
+        40 => Some(BmpHeaderVersion::Info),
+        52 => Some(BmpHeaderVersion::V2),
+        56 => Some(BmpHeaderVersion::V3),
+        108 => Some(BmpHeaderVersion::V4),
+        124 => Some(BmpHeaderVersion::V5),
+        // OS/2 2.x headers may be truncated after any field
+        16..=64 => Some(BmpHeaderVersion::Os2V2),
+        _ => None,
+    }
+}
+
 // https://www.fileformat.info/format/bmp/corion.htm
 pub fn try_bmp<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<ImageInfo>
 where
@@ -14,21 +30,124 @@ where
         return Err(ImageInfoError::UnrecognizedFormat);
     }
 
-    let mut ret = ImageInfo {
-        format: ImageFormat::BMP,
-        ext: "bmp",
-        full_ext: "bmp",
-        mimetype: "image/bmp",
-        size: ImageSize {
+    let mut ret = ImageInfo::from_format(
+        ImageFormat::BMP,
+        ImageSize {
             width: 0,
             height: 0,
         },
-        entry_sizes: vec![],
-    };
+    );
 
-    ret.size.width = buffer.read_i32_le(18) as i64;
-    // bmp height can be negative, it means flip Y
-    ret.size.height = buffer.read_i32_le(22).abs() as i64;
+    match bmp_header_version(buffer.read_u32_le(14)) {
+        Some(BmpHeaderVersion::Core) => {
+            ret.size.width = buffer.read_u16_le(18) as i64;
+            ret.size.height = buffer.read_u16_le(20) as i64;
+        }
+        // unknown header sizes are read like BITMAPINFOHEADER
+        _ => {
+            ret.size.width = buffer.read_i32_le(18) as i64;
+            // bmp height can be negative, it means flip Y
+            ret.size.height = buffer.read_i32_le(22).unsigned_abs() as i64;
+        }
+    }
 
     Ok(ret)
 }
+
+fn compression(value: u32, header_version: &BmpHeaderVersion) -> BmpCompression {
+    match (value, header_version) {
+        (0, _) => BmpCompression::Rgb,
+        (1, _) => BmpCompression::Rle8,
+        (2, _) => BmpCompression::Rle4,
+        (3, BmpHeaderVersion::Os2V2) => BmpCompression::Huffman1D,
+        (3, _) => BmpCompression::Bitfields,
+        (4, BmpHeaderVersion::Os2V2) => BmpCompression::Rle24,
+        (4, _) => BmpCompression::Jpeg,
+        (5, _) => BmpCompression::Png,
+        (6, _) => BmpCompression::AlphaBitfields,
+        (11, _) => BmpCompression::Cmyk,
+        (12, _) => BmpCompression::CmykRle8,
+        (13, _) => BmpCompression::CmykRle4,
+        _ => BmpCompression::Unknown(value),
+    }
+}
+
+pub fn read_bmp_details<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<BmpDetails>
+where
+    R: BufRead + Seek,
+{
+    if length < 26 {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    let buffer = ri.read(0, 18)?;
+    let data_offset = buffer.read_u32_le(10);
+    let header_size = buffer.read_u32_le(14);
+    let header_version =
+        bmp_header_version(header_size).ok_or(ImageInfoError::UnrecognizedFormat)?;
+    let header = ri.read(14, min(header_size as usize, length - 14))?;
+
+    if header_version == BmpHeaderVersion::Core {
+        let bit_count = header.read_u16_le(10);
+        return Ok(BmpDetails {
+            header_version,
+            header_size,
+            bit_count,
+            compression: BmpCompression::Rgb,
+            palette_size: if (1..=8).contains(&bit_count) {
+                1 << bit_count
+            } else {
+                0
+            },
+            top_down: false,
+            data_offset,
+            icc_profile: None,
+        });
+    }
+
+    // fields after the dimensions are optional in truncated OS/2 2.x headers
+    let field = |offset: usize| {
+        if offset + 4 <= header.len() {
+            header.read_u32_le(offset)
+        } else {
+            0
+        }
+    };
+    let bit_count = if header.len() >= 16 {
+        header.read_u16_le(14)
+    } else {
+        0
+    };
+    let colors_used = field(32);
+    // a bit count of 0 leaves it to the JPEG or PNG stream, no palette
+    let palette_size = if colors_used != 0 {
+        colors_used
+    } else if (1..=8).contains(&bit_count) {
+        1 << bit_count
+    } else {
+        0
+    };
+
+    // LCS_PROFILE_EMBEDDED, the profile offset counts from the info header
+    let mut icc_profile = None;
+    if header_version == BmpHeaderVersion::V5 && field(56) == 0x4D424544 {
+        let offset = 14 + field(112) as u64;
+        let profile_length = field(116) as u64;
+        if profile_length > 0 && offset + profile_length <= length as u64 {
+            icc_profile = Some(BmpIccProfile {
+                offset,
+                length: profile_length,
+            });
+        }
+    }
+
+    Ok(BmpDetails {
+        compression: compression(field(16), &header_version),
+        header_version,
+        header_size,
+        bit_count,
+        palette_size,
+        top_down: header.read_i32_le(8) < 0,
+        data_offset,
+        icc_profile,
+    })
+}
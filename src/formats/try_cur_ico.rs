@@ -1,3 +1,4 @@
+use crate::details::{CursorHotspot, IcoEntry, IcoPayload};
 use crate::{ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface};
 use std::io::{BufRead, Seek};
 
@@ -13,37 +14,52 @@ where
     let mut ret =
         // ico type == 1
         if buffer.cmp(0, 4, b"\x00\x00\x01\x00") {
-            ImageInfo {
-                format: ImageFormat::ICO,
-                ext: "ico",
-                full_ext: "ico",
-                mimetype: "image/ico",
-                size: ImageSize {
-                    width: 0,
-                    height: 0,
-                },
-                entry_sizes: vec![],
-            }
+            ImageInfo::from_format(ImageFormat::ICO, ImageSize {
+                width: 0,
+                height: 0,
+            })
         }
         // cur type == 2
         else if buffer.cmp(0, 4, b"\x00\x00\x02\x00") {
-            ImageInfo {
-                format: ImageFormat::CUR,
-                ext: "cur",
-                full_ext: "cur",
-                mimetype: "image/cur",
-                size: ImageSize {
-                    width: 0,
-                    height: 0,
-                },
-                entry_sizes: vec![],
-            }
+            ImageInfo::from_format(ImageFormat::CUR, ImageSize {
+                width: 0,
+                height: 0,
+            })
         }
         // invalid
         else {
             return Err(ImageInfoError::UnrecognizedFormat);
         };
 
+    let is_cursor = ret.format == ImageFormat::CUR;
+    let entries = read_ico_entries(ri, length, is_cursor)?;
+
+    // the largest entry is the main image, the first one of equal areas.
+    // PNG payloads report u32 sizes, saturate instead of overflowing
+    let largest = entries
+        .iter()
+        .rev()
+        .max_by_key(|entry| entry.size.width.saturating_mul(entry.size.height))
+        .ok_or(ImageInfoError::UnrecognizedFormat)?;
+    ret.size.width = largest.size.width;
+    ret.size.height = largest.size.height;
+    for entry in entries {
+        ret.entry_sizes.push(entry.size);
+    }
+
+    Ok(ret)
+}
+
+// https://en.wikipedia.org/wiki/ICO_(file_format)
+pub fn read_ico_entries<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    is_cursor: bool,
+) -> ImageInfoResult<Vec<IcoEntry>>
+where
+    R: BufRead + Seek,
+{
+    let buffer = ri.read(0, 6)?;
     let entry_count = buffer.read_u16_le(4) as usize;
     if entry_count == 0 {
         return Err(ImageInfoError::UnrecognizedFormat);
@@ -52,31 +68,92 @@ where
     let entry_size = 16;
     let entry_total_size = entry_count * entry_size;
 
-    let mut offset = 6usize;
+    let offset = 6usize;
     if length < offset + entry_total_size {
         return Err(ImageInfoError::UnrecognizedFormat);
     }
 
     let buffer = ri.read(offset, entry_total_size)?;
-    offset += entry_total_size;
 
+    let mut entries = vec![];
     for i in 0..entry_count {
-        let width = buffer.read_u8(i * entry_size);
-        let height = buffer.read_u8(i * entry_size + 1);
+        let entry_offset = i * entry_size;
+        let width = buffer.read_u8(entry_offset);
+        let height = buffer.read_u8(entry_offset + 1);
         let width = if width == 0 { 256i64 } else { width as i64 };
         let height = if height == 0 { 256i64 } else { height as i64 };
-        ret.entry_sizes.push(ImageSize { width, height });
+        let color_count = buffer.read_u8(entry_offset + 2);
+        // planes and bit count for icons, hotspot for cursors
+        let field1 = buffer.read_u16_le(entry_offset + 4);
+        let field2 = buffer.read_u16_le(entry_offset + 6);
+        let byte_size = buffer.read_u32_le(entry_offset + 8);
+        let data_offset = buffer.read_u32_le(entry_offset + 12);
 
-        let bytes = buffer.read_i32_le(i * entry_size + 8) as usize;
-        offset += bytes;
-    }
+        let (payload, size) = read_payload(ri, length, data_offset as usize, byte_size as usize)?
+            .unwrap_or((IcoPayload::Unknown, ImageSize { width, height }));
 
-    if let Some(size) = ret.entry_sizes.first() {
-        ret.size.width = size.width;
-        ret.size.height = size.height;
-    } else {
-        return Err(ImageInfoError::UnrecognizedFormat);
+        entries.push(IcoEntry {
+            size,
+            color_count,
+            planes: if is_cursor { None } else { Some(field1) },
+            bit_count: if is_cursor { None } else { Some(field2) },
+            hotspot: if is_cursor {
+                Some(CursorHotspot {
+                    x: field1,
+                    y: field2,
+                })
+            } else {
+                None
+            },
+            byte_size,
+            offset: data_offset,
+            payload,
+        });
     }
+    Ok(entries)
+}
 
-    Ok(ret)
+// The directory caps sizes at 256, the payload header has the real one
+fn read_payload<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    offset: usize,
+    size: usize,
+) -> ImageInfoResult<Option<(IcoPayload, ImageSize)>>
+where
+    R: BufRead + Seek,
+{
+    if size < 24 || offset.saturating_add(size) > length {
+        return Ok(None);
+    }
+    let buffer = ri.read(offset, 24)?;
+    if buffer.cmp(0, 8, b"\x89PNG\r\n\x1A\n") && buffer.cmp(12, 4, b"IHDR") {
+        return Ok(Some((
+            IcoPayload::Png,
+            ImageSize {
+                width: buffer.read_u32_be(16) as i64,
+                height: buffer.read_u32_be(20) as i64,
+            },
+        )));
+    }
+    // the height of a DIB covers both the XOR and the AND mask
+    let header_size = buffer.read_u32_le(0);
+    let (width, height) = match header_size {
+        12 => (buffer.read_u16_le(4) as i64, buffer.read_u16_le(6) as i64),
+        40 | 52 | 56 | 64 | 108 | 124 => (
+            buffer.read_i32_le(4) as i64,
+            buffer.read_i32_le(8).unsigned_abs() as i64,
+        ),
+        _ => return Ok(None),
+    };
+    if width <= 0 || height < 2 {
+        return Ok(None);
+    }
+    Ok(Some((
+        IcoPayload::Dib,
+        ImageSize {
+            width,
+            height: height / 2,
+        },
+    )))
 }
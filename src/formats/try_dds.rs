@@ -13,15 +13,28 @@ where
         return Err(ImageInfoError::UnrecognizedFormat);
     }
 
-    Ok(ImageInfo {
-        format: ImageFormat::DDS,
-        ext: "dds",
-        full_ext: "dds",
-        mimetype: "image/dds",
-        size: ImageSize {
+    Ok(ImageInfo::from_format(
+        ImageFormat::DDS,
+        ImageSize {
             width: buffer.read_u32_le(16) as i64,
             height: buffer.read_u32_le(12) as i64,
         },
-        entry_sizes: vec![],
-    })
+    ))
+}
+
+// Mipmap levels including the full size one, 1 without DDSD_MIPMAPCOUNT
+//
+// https://learn.microsoft.com/en-us/windows/win32/direct3ddds/dds-header
+pub fn read_dds_mip_count<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<u32>
+where
+    R: BufRead + Seek,
+{
+    if length < 32 {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    let buffer = ri.read(0, 32)?;
+    if buffer.read_u32_le(8) & 0x20000 == 0 {
+        return Ok(1);
+    }
+    Ok(buffer.read_u32_le(28).max(1))
 }
@@ -1,4 +1,6 @@
+use crate::details::{GifDetails, GifFrame};
 use crate::{ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface};
+use std::cmp::min;
 use std::io::{BufRead, Seek};
 
 // https://www.fileformat.info/format/gif/corion.htm
@@ -14,20 +16,210 @@ where
         return Err(ImageInfoError::UnrecognizedFormat);
     }
 
-    let mut ret = ImageInfo {
-        format: ImageFormat::GIF,
-        ext: "gif",
-        full_ext: "gif",
-        mimetype: "image/gif",
-        size: ImageSize {
+    let mut ret = ImageInfo::from_format(
+        ImageFormat::GIF,
+        ImageSize {
             width: 0,
             height: 0,
         },
-        entry_sizes: vec![],
-    };
+    );
 
     ret.size.width = buffer.read_u16_le(6) as i64;
     ret.size.height = buffer.read_u16_le(8) as i64;
 
     Ok(ret)
 }
+
+// Offset after the data sub-blocks starting at offset, None if truncated.
+// The length bytes are walked in chunks growing up to 64 KiB, rather than
+// with a read per sub-block
+fn skip_sub_blocks<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    mut offset: usize,
+) -> ImageInfoResult<Option<usize>>
+where
+    R: BufRead + Seek,
+{
+    let mut chunk_size = 1024;
+    while offset < length {
+        let chunk_start = offset;
+        let chunk = ri.read(chunk_start, min(chunk_size, length - chunk_start))?;
+        while offset < chunk_start + chunk.len() {
+            let block_size = chunk.read_u8(offset - chunk_start) as usize;
+            offset += 1 + block_size;
+            if block_size == 0 {
+                return Ok(Some(offset));
+            }
+        }
+        chunk_size = min(chunk_size * 2, 64 * 1024);
+    }
+    Ok(None)
+}
+
+fn color_table_size(flags: u8) -> Option<u16> {
+    if flags & 0x80 != 0 {
+        Some(2 << (flags & 0x07))
+    } else {
+        None
+    }
+}
+
+// Walks the blocks up to the trailer, stopping quietly at truncated data
+pub fn read_gif_details<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<GifDetails>
+where
+    R: BufRead + Seek,
+{
+    Ok(read_gif_blocks(ri, length)?.0)
+}
+
+// Offset just past the trailer, None if the data ends before it
+pub fn find_gif_end<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<Option<usize>>
+where
+    R: BufRead + Seek,
+{
+    Ok(read_gif_blocks(ri, length)?.1)
+}
+
+// Details and the end of the trailer
+fn read_gif_blocks<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+) -> ImageInfoResult<(GifDetails, Option<usize>)>
+where
+    R: BufRead + Seek,
+{
+    if length < 13 {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    let buffer = ri.read(0, 13)?;
+    if !buffer.cmp_any_of(0, 6, vec![b"GIF87a", b"GIF89a"]) {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    let flags = buffer.read_u8(10);
+    let global_color_table_size = color_table_size(flags);
+    let mut details = GifDetails {
+        version: buffer.read_str(3, 3),
+        global_color_table_size,
+        background_color_index: buffer.read_u8(11),
+        pixel_aspect_ratio: buffer.read_u8(12),
+        frames: vec![],
+        application_extensions: vec![],
+        loop_count: None,
+        xmp: None,
+        has_trailer: false,
+    };
+
+    let mut end = None;
+    // graphic control extension of the next image
+    let mut control: Option<(u8, u16, Option<u8>)> = None;
+    let mut offset = 13 + 3 * global_color_table_size.unwrap_or(0) as usize;
+    while offset < length {
+        let introducer = ri.read(offset, 1)?.read_u8(0);
+        let next = match introducer {
+            // image descriptor
+            0x2C if offset + 10 <= length => {
+                let buffer = ri.read(offset, 10)?;
+                let flags = buffer.read_u8(9);
+                let local_color_table_size = color_table_size(flags);
+                let (disposal_method, delay, transparent_index) =
+                    control.take().unwrap_or((0, 0, None));
+                details.frames.push(GifFrame {
+                    left: buffer.read_u16_le(1),
+                    top: buffer.read_u16_le(3),
+                    width: buffer.read_u16_le(5),
+                    height: buffer.read_u16_le(7),
+                    interlaced: flags & 0x40 != 0,
+                    local_color_table_size,
+                    disposal_method,
+                    delay,
+                    transparent_index,
+                });
+                // LZW minimum code size, then the image data
+                let data_offset =
+                    offset + 10 + 3 * local_color_table_size.unwrap_or(0) as usize + 1;
+                skip_sub_blocks(ri, length, data_offset)?
+            }
+            // extension
+            0x21 if offset + 3 <= length => {
+                let buffer = ri.read(offset, 3)?;
+                let label = buffer.read_u8(1);
+                let block_size = buffer.read_u8(2) as usize;
+                let block_offset = offset + 3;
+                if block_offset + block_size > length {
+                    None
+                } else {
+                    let block = ri.read(block_offset, block_size)?;
+                    if label == 0xF9 && block_size == 4 {
+                        let flags = block.read_u8(0);
+                        control = Some((
+                            (flags >> 2) & 0x07,
+                            block.read_u16_le(1),
+                            if flags & 0x01 != 0 {
+                                Some(block.read_u8(3))
+                            } else {
+                                None
+                            },
+                        ));
+                    } else if label == 0xFF && block_size == 11 {
+                        details.application_extensions.push(block.read_str_all());
+                    }
+                    let data_offset = block_offset + block_size;
+                    let end = skip_sub_blocks(ri, length, data_offset)?;
+                    if label == 0xFF && block_size == 11 {
+                        if let Some(end) = end {
+                            read_application_data(
+                                ri,
+                                &block.read_str_all(),
+                                data_offset,
+                                end,
+                                &mut details,
+                            )?;
+                        }
+                    }
+                    end
+                }
+            }
+            0x3B => {
+                details.has_trailer = true;
+                end = Some(offset + 1);
+                break;
+            }
+            // truncated block or not a block at all
+            _ => None,
+        };
+        match next {
+            Some(next) => offset = next,
+            None => break,
+        }
+    }
+
+    Ok((details, end))
+}
+
+fn read_application_data<R>(
+    ri: &mut ReadInterface<R>,
+    identifier: &str,
+    offset: usize,
+    end: usize,
+    details: &mut GifDetails,
+) -> ImageInfoResult<()>
+where
+    R: BufRead + Seek,
+{
+    match identifier {
+        // sub-block of 3 bytes: 1, then the loop count
+        "NETSCAPE2.0" | "ANIMEXTS1.0" if end - offset >= 5 => {
+            let buffer = ri.read(offset, 4)?;
+            if buffer.read_u8(0) == 3 && buffer.read_u8(1) == 1 {
+                details.loop_count = Some(buffer.read_u16_le(2));
+            }
+        }
+        // raw packet followed by a 257-byte "magic trailer" and the terminator
+        "XMP DataXMP" if end - offset >= 258 => {
+            details.xmp = Some(ri.read(offset, end - offset - 258)?.read_str_all());
+        }
+        _ => {}
+    }
+    Ok(())
+}
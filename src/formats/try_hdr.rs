@@ -3,8 +3,13 @@ use std::cmp::min;
 use std::io::{BufRead, Seek};
 use std::str::FromStr;
 
+// Header lines before the blank line, and the resolution string after it
+//
 // http://paulbourke.net/dataformats/pic/
-pub fn try_hdr<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<ImageInfo>
+pub fn read_radiance_header<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+) -> ImageInfoResult<(Vec<String>, String)>
 where
     R: BufRead + Seek,
 {
@@ -25,7 +30,6 @@ where
     let piece = 64usize;
     let mut header = String::new();
     let mut resolution_start = 0usize;
-    let mut resolution = "";
     let mut offset = 0usize;
     while offset < length {
         let buffer = ri.read(offset, min(length - offset, piece))?;
@@ -39,31 +43,53 @@ where
             }
         }
         if let Some(pos) = &header[resolution_start..].find('\n') {
-            resolution = &header[resolution_start..resolution_start + pos];
-            break;
+            let lines = header[..resolution_start - 2]
+                .lines()
+                .map(|line| line.to_string())
+                .collect();
+            let resolution = header[resolution_start..resolution_start + pos].to_string();
+            return Ok((lines, resolution));
         }
     }
-    if resolution.is_empty() {
-        return Err(ImageInfoError::UnrecognizedFormat);
-    }
-    let tokens: Vec<&str> = resolution.split(' ').collect();
+    Err(ImageInfoError::UnrecognizedFormat)
+}
+
+// Size and orientation of a resolution string such as "-Y 512 +X 1024".
+// Width is the X value and height the Y value whatever their order; X first
+// means scanlines run vertically (transposed). The orientation uses the EXIF
+// values, 1 being the standard "-Y h +X w".
+pub fn parse_radiance_resolution(resolution: &str) -> Option<(ImageSize, u16)> {
+    let tokens: Vec<&str> = resolution.split_whitespace().collect();
     if tokens.len() != 4 {
-        return Err(ImageInfoError::UnrecognizedFormat);
+        return None;
     }
-    let y_str = tokens[1];
-    let x_str = tokens[3];
+    let first = (tokens[0], i64::from_str(tokens[1]).ok()?);
+    let second = (tokens[2], i64::from_str(tokens[3]).ok()?);
+    let (width, height) = match (first.0, second.0) {
+        ("-Y" | "+Y", "-X" | "+X") => (second.1, first.1),
+        ("-X" | "+X", "-Y" | "+Y") => (first.1, second.1),
+        _ => return None,
+    };
+    let orientation = match (first.0, second.0) {
+        ("-Y", "+X") => 1,
+        ("-Y", "-X") => 2,
+        ("+Y", "-X") => 3,
+        ("+Y", "+X") => 4,
+        ("+X", "-Y") => 5,
+        ("-X", "-Y") => 6,
+        ("-X", "+Y") => 7,
+        _ => 8,
+    };
+    Some((ImageSize { width, height }, orientation))
+}
 
-    if let Ok(width) = i64::from_str(x_str) {
-        if let Ok(height) = i64::from_str(y_str) {
-            return Ok(ImageInfo {
-                format: ImageFormat::HDR,
-                ext: "hdr",
-                full_ext: "hdr",
-                mimetype: "image/vnd.radiance",
-                size: ImageSize { width, height },
-                entry_sizes: vec![],
-            });
-        }
+pub fn try_hdr<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<ImageInfo>
+where
+    R: BufRead + Seek,
+{
+    let (_, resolution) = read_radiance_header(ri, length)?;
+    match parse_radiance_resolution(&resolution) {
+        Some((size, _)) => Ok(ImageInfo::from_format(ImageFormat::HDR, size)),
+        None => Err(ImageInfoError::UnrecognizedFormat),
     }
-    Err(ImageInfoError::UnrecognizedFormat)
 }
@@ -1,9 +1,46 @@
+use crate::details::{IcnsEntry, IcnsPayload};
+use crate::preview::probe_embedded;
 use crate::{ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface};
-use std::cmp::max;
 use std::collections::HashMap;
 use std::io::{BufRead, Seek};
 
 pub fn try_icns<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<ImageInfo>
+where
+    R: BufRead + Seek,
+{
+    let entries = read_icns_entries(ri, length)?;
+
+    let mut ret = ImageInfo::from_format(
+        ImageFormat::ICNS,
+        ImageSize {
+            width: 0,
+            height: 0,
+        },
+    );
+
+    for entry in entries {
+        if let Some(size) = entry.size {
+            // embedded PNG and JPEG 2000 sizes go up to u32
+            let area = size.width.saturating_mul(size.height);
+            if area > ret.size.width.saturating_mul(ret.size.height) {
+                ret.size.width = size.width;
+                ret.size.height = size.height;
+            }
+            ret.entry_sizes.push(size);
+        }
+    }
+    if ret.entry_sizes.is_empty() {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+
+    Ok(ret)
+}
+
+// https://en.wikipedia.org/wiki/Apple_Icon_Image_format
+pub fn read_icns_entries<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+) -> ImageInfoResult<Vec<IcnsEntry>>
 where
     R: BufRead + Seek,
 {
@@ -16,79 +53,116 @@ where
         return Err(ImageInfoError::UnrecognizedFormat);
     }
 
-    let type_size_map: HashMap<&str, i64> = [
-        ("ICON", 32),
-        ("ICN#", 32),
-        ("icm#", 16),
-        ("icm4", 16),
-        ("icm8", 16),
-        ("ics#", 16),
-        ("ics4", 16),
-        ("ics8", 16),
-        ("is32", 16),
-        ("s8mk", 16),
-        ("icl4", 32),
-        ("icl8", 32),
-        ("il32", 32),
-        ("l8mk", 32),
-        ("ich#", 48),
-        ("ich4", 48),
-        ("ich8", 48),
-        ("ih32", 48),
-        ("h8mk", 48),
-        ("it32", 128),
-        ("t8mk", 128),
-        ("icp4", 16),
-        ("icp5", 32),
-        ("icp6", 64),
-        ("ic07", 128),
-        ("ic08", 256),
-        ("ic09", 512),
-        ("ic10", 1024),
-        ("ic11", 32),
-        ("ic12", 64),
-        ("ic13", 256),
-        ("ic14", 512),
-        ("ic04", 16),
-        ("ic05", 32),
-        ("icsB", 36),
-        ("icsb", 18),
+    // Edge length in points, scale factor and the payload if not PNG/JPEG 2000/ARGB
+    let type_map: HashMap<&str, (i64, u8, IcnsPayload)> = [
+        ("ICON", (32, 1, IcnsPayload::Bitmap)),
+        ("ICN#", (32, 1, IcnsPayload::Bitmap)),
+        ("icm#", (16, 1, IcnsPayload::Bitmap)),
+        ("icm4", (16, 1, IcnsPayload::Indexed)),
+        ("icm8", (16, 1, IcnsPayload::Indexed)),
+        ("ics#", (16, 1, IcnsPayload::Bitmap)),
+        ("ics4", (16, 1, IcnsPayload::Indexed)),
+        ("ics8", (16, 1, IcnsPayload::Indexed)),
+        ("is32", (16, 1, IcnsPayload::Rle)),
+        ("s8mk", (16, 1, IcnsPayload::Mask)),
+        ("icl4", (32, 1, IcnsPayload::Indexed)),
+        ("icl8", (32, 1, IcnsPayload::Indexed)),
+        ("il32", (32, 1, IcnsPayload::Rle)),
+        ("l8mk", (32, 1, IcnsPayload::Mask)),
+        ("ich#", (48, 1, IcnsPayload::Bitmap)),
+        ("ich4", (48, 1, IcnsPayload::Indexed)),
+        ("ich8", (48, 1, IcnsPayload::Indexed)),
+        ("ih32", (48, 1, IcnsPayload::Rle)),
+        ("h8mk", (48, 1, IcnsPayload::Mask)),
+        ("it32", (128, 1, IcnsPayload::Rle)),
+        ("t8mk", (128, 1, IcnsPayload::Mask)),
+        ("icp4", (16, 1, IcnsPayload::Rle)),
+        ("icp5", (32, 1, IcnsPayload::Rle)),
+        ("icp6", (64, 1, IcnsPayload::Png)),
+        ("ic07", (128, 1, IcnsPayload::Png)),
+        ("ic08", (256, 1, IcnsPayload::Png)),
+        ("ic09", (512, 1, IcnsPayload::Png)),
+        ("ic10", (512, 2, IcnsPayload::Png)),
+        ("ic11", (16, 2, IcnsPayload::Png)),
+        ("ic12", (32, 2, IcnsPayload::Png)),
+        ("ic13", (128, 2, IcnsPayload::Png)),
+        ("ic14", (256, 2, IcnsPayload::Png)),
+        ("ic04", (16, 1, IcnsPayload::Argb)),
+        ("ic05", (32, 1, IcnsPayload::Argb)),
+        ("icsB", (18, 2, IcnsPayload::Png)),
+        ("icsb", (18, 1, IcnsPayload::Argb)),
     ]
     .iter()
     .cloned()
     .collect();
 
-    let mut ret = ImageInfo {
-        format: ImageFormat::ICNS,
-        ext: "icns",
-        full_ext: "icns",
-        mimetype: "image/icns",
-        size: ImageSize {
-            width: 0,
-            height: 0,
-        },
-        entry_sizes: vec![],
-    };
-
-    let mut max_size = 0i64;
+    let mut entries = vec![];
     let mut offset = 8usize;
-    while offset + 8 <= length {
+    while offset + 8 <= file_length {
         let buffer = ri.read(offset, 8)?;
-        let t = buffer.read_str(0, 4);
+        let os_type = buffer.read_str(0, 4);
         let entry_size = buffer.read_u32_be(4) as usize;
-        if let Some(s) = type_size_map.get(t.as_str()) {
-            ret.entry_sizes.push(ImageSize {
-                width: *s,
-                height: *s,
-            });
-            max_size = max(max_size, *s);
-            offset += entry_size;
-        } else {
+        if entry_size < 8 || offset + entry_size > file_length {
             return Err(ImageInfoError::UnrecognizedFormat);
         }
-    }
-    ret.size.width = max_size;
-    ret.size.height = max_size;
+        let data_offset = offset + 8;
+        let data_length = entry_size - 8;
 
-    Ok(ret)
+        // table of contents, version, name, info plist, template and
+        // selected variants, dark mode icon set and any type yet unknown
+        let (points, scale, default_payload) = match type_map.get(os_type.as_str()) {
+            Some(&(points, scale, payload)) => (points, scale, payload),
+            None => {
+                entries.push(IcnsEntry {
+                    os_type,
+                    offset: data_offset as u64,
+                    length: data_length as u64,
+                    scale: 1,
+                    payload: IcnsPayload::Other,
+                    size: None,
+                });
+                offset += entry_size;
+                continue;
+            }
+        };
+
+        let mut payload = default_payload;
+        let mut size = ImageSize {
+            width: points * scale as i64,
+            height: points * scale as i64,
+        };
+        if data_length >= 4 {
+            let buffer = ri.read(data_offset, data_length.min(12))?;
+            if buffer.cmp(0, 4, b"ARGB") {
+                payload = IcnsPayload::Argb;
+            } else if let Some(info) = probe_embedded(ri, data_offset, data_length)? {
+                match info.format {
+                    ImageFormat::PNG => {
+                        payload = IcnsPayload::Png;
+                        size = info.size;
+                    }
+                    ImageFormat::JP2 | ImageFormat::J2K | ImageFormat::JPX | ImageFormat::JPH => {
+                        payload = IcnsPayload::Jpeg2000;
+                        size = info.size;
+                    }
+                    // a JPEG, which icons don't hold
+                    _ => payload = IcnsPayload::Other,
+                }
+            } else if default_payload == IcnsPayload::Png {
+                // neither PNG nor JPEG 2000 in a slot that requires one of them
+                payload = IcnsPayload::Other;
+            }
+        }
+
+        entries.push(IcnsEntry {
+            os_type,
+            offset: data_offset as u64,
+            length: data_length as u64,
+            scale,
+            payload,
+            size: Some(size),
+        });
+        offset += entry_size;
+    }
+    Ok(entries)
 }
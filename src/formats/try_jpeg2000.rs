@@ -1,6 +1,87 @@
-use crate::{ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface};
+use crate::details::{Jpeg2000Details, Jpeg2000ReaderRequirements, Jpeg2000StandardFeature};
+use crate::formats::{read_jpeg2000_code_stream, try_jpeg2000_code_stream};
+use crate::{
+    ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize, RawBuffer, ReadInterface,
+};
 use std::io::{BufRead, Seek};
 
+// Box type and the offset and length of its content
+struct Jp2Box {
+    box_type: [u8; 4],
+    offset: usize,
+    length: usize,
+}
+
+// Boxes between offset and end, up to the first malformed one
+fn read_boxes<R>(
+    ri: &mut ReadInterface<R>,
+    mut offset: usize,
+    end: usize,
+) -> ImageInfoResult<Vec<Jp2Box>>
+where
+    R: BufRead + Seek,
+{
+    let mut boxes = vec![];
+    while offset + 8 <= end {
+        let buffer = ri.read(offset, 8)?;
+        let (box_length, header_length) = match buffer.read_u32_be(0) {
+            // up to the end of the enclosing box or file
+            0 => ((end - offset) as u64, 8),
+            // XL box, 64-bit length after the type
+            1 if offset + 16 <= end => (ri.read(offset + 8, 8)?.read_u64_be(0), 16),
+            1 => break,
+            box_length => (box_length as u64, 8),
+        };
+        if box_length < header_length as u64 || (offset as u64) + box_length > end as u64 {
+            break;
+        }
+        let box_length = box_length as usize;
+        let mut box_type = [0u8; 4];
+        box_type.copy_from_slice(buffer.piece(4, 4));
+        boxes.push(Jp2Box {
+            box_type,
+            offset: offset + header_length,
+            length: box_length - header_length,
+        });
+        offset += box_length;
+    }
+    Ok(boxes)
+}
+
+// Contiguous codestreams (jp2c) and in-file fragmented ones (ftbl), in
+// file order, as the offset and length of their first byte range
+fn find_code_streams<R>(
+    ri: &mut ReadInterface<R>,
+    boxes: &[Jp2Box],
+) -> ImageInfoResult<Vec<(usize, usize)>>
+where
+    R: BufRead + Seek,
+{
+    let mut code_streams = vec![];
+    for b in boxes {
+        if &b.box_type == b"jp2c" {
+            code_streams.push((b.offset, b.length));
+        } else if &b.box_type == b"ftbl" {
+            // flst: count, then offset (64-bit), length and data reference
+            // of each fragment, 0 for this file
+            let children = read_boxes(ri, b.offset, b.offset + b.length)?;
+            let flst = children.iter().find(|child| &child.box_type == b"flst");
+            if let Some(flst) = flst.filter(|flst| flst.length >= 16) {
+                let buffer = ri.read(flst.offset, 16)?;
+                let offset = buffer.read_u64_be(2) as usize;
+                let length = buffer.read_u32_be(10) as usize;
+                if buffer.read_u16_be(0) > 0
+                    && buffer.read_u16_be(14) == 0
+                    && offset.saturating_add(length) <= ri.length
+                {
+                    code_streams.push((offset, length));
+                }
+            }
+        }
+    }
+    Ok(code_streams)
+}
+
 // https://docs.fileformat.com/image/jp2/
 // https://docs.fileformat.com/image/jpx/
 pub fn try_jpeg2000<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<ImageInfo>
@@ -16,7 +97,7 @@ where
     }
 
     let signature_length = buffer.read_u32_be(0) as usize;
-    let mut offset = signature_length;
+    let offset = signature_length;
     if length < offset + 12 {
         return Err(ImageInfoError::UnrecognizedFormat);
     }
@@ -30,68 +111,168 @@ where
     let mut ret =
         // type == jp2
         if buffer.cmp(8, 4, b"jp2 ") {
-            ImageInfo {
-                format: ImageFormat::JP2,
-                ext: "jp2",
-                full_ext: "jp2",
-                mimetype: "image/jp2",
-                size: ImageSize {
-                    width: 0,
-                    height: 0,
-                },
-                entry_sizes: vec![],
-            }
+            ImageInfo::from_format(ImageFormat::JP2, ImageSize {
+                width: 0,
+                height: 0,
+            })
         }
         // type == jph
         else if buffer.cmp(8, 4, b"jph ") {
-            ImageInfo {
-                format: ImageFormat::JPH,
-                ext: "jph",
-                full_ext: "jph",
-                mimetype: "image/jph",
-                size: ImageSize {
-                    width: 0,
-                    height: 0,
-                },
-                entry_sizes: vec![],
-            }
+            ImageInfo::from_format(ImageFormat::JPH, ImageSize {
+                width: 0,
+                height: 0,
+            })
         }
         // type == jpx
         else if buffer.cmp(8, 4, b"jpx ") {
-            ImageInfo {
-                format: ImageFormat::JPX,
-                ext: "jpx",
-                full_ext: "jpx",
-                mimetype: "image/jpx",
-                size: ImageSize {
-                    width: 0,
-                    height: 0,
-                },
-                entry_sizes: vec![],
-            }
+            ImageInfo::from_format(ImageFormat::JPX, ImageSize {
+                width: 0,
+                height: 0,
+            })
         }
         // invalid
         else {
             return Err(ImageInfoError::UnrecognizedFormat);
         };
 
-    let ftyp_length = buffer.read_u32_be(0) as usize;
-    offset += ftyp_length;
-
-    while offset + 24 <= length {
-        let buffer = ri.read(offset, 24)?;
-        if buffer.cmp(4, 4, b"jp2h") {
-            if buffer.cmp(12, 4, b"ihdr") {
-                ret.size.width = buffer.read_u32_be(20) as i64;
-                ret.size.height = buffer.read_u32_be(16) as i64;
-                return Ok(ret);
-            } else {
-                return Err(ImageInfoError::UnrecognizedFormat);
-            }
+    let boxes = read_boxes(ri, 0, length)?;
+
+    // ihdr of the JP2 header box: height, then width
+    let mut header_size = None;
+    if let Some(jp2h) = boxes.iter().find(|b| &b.box_type == b"jp2h") {
+        let children = read_boxes(ri, jp2h.offset, jp2h.offset + jp2h.length)?;
+        if let Some(ihdr) = children
+            .iter()
+            .find(|b| &b.box_type == b"ihdr" && b.length >= 8)
+        {
+            let buffer = ri.read(ihdr.offset, 8)?;
+            header_size = Some(ImageSize {
+                width: buffer.read_u32_be(4) as i64,
+                height: buffer.read_u32_be(0) as i64,
+            });
         }
-        let box_length = buffer.read_u32_be(0) as usize;
-        offset += box_length;
     }
 
-    Err(ImageInfoError::UnrecognizedFormat)
+    let mut code_stream_sizes = vec![];
+    for (offset, code_stream_length) in find_code_streams(ri, &boxes)? {
+        let mut sub = ri.sub_interface(offset, code_stream_length);
+        match try_jpeg2000_code_stream(&mut sub, code_stream_length) {
+            Ok(info) => code_stream_sizes.push(info.size),
+            Err(ImageInfoError::UnrecognizedFormat) => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    ret.size = match header_size {
+        Some(size) => size,
+        None if !code_stream_sizes.is_empty() => ImageSize {
+            width: code_stream_sizes[0].width,
+            height: code_stream_sizes[0].height,
+        },
+        None => return Err(ImageInfoError::UnrecognizedFormat),
+    };
+    // like other formats, a single image has no entries
+    if code_stream_sizes.len() > 1 {
+        ret.entry_sizes = code_stream_sizes;
+    }
+    Ok(ret)
+}
+
+// rreq: mask length, fully understand and decode completely masks, then
+// the standard and vendor features with their masks. Only the parsed fields
+// are read, None if they are truncated, and a longer box is rejected
+fn read_reader_requirements<R>(
+    ri: &mut ReadInterface<R>,
+    rreq: &Jp2Box,
+) -> ImageInfoResult<Option<Jpeg2000ReaderRequirements>>
+where
+    R: BufRead + Seek,
+{
+    let end = rreq.offset + rreq.length;
+    if rreq.length < 1 {
+        return Ok(None);
+    }
+    let mask_length = ri.read(rreq.offset, 1)?.read_u8(0) as usize;
+    if mask_length == 0 || mask_length > 8 {
+        return Ok(None);
+    }
+    let read_mask = |buffer: &RawBuffer, start: usize| {
+        (0..mask_length).fold(0u64, |mask, i| {
+            (mask << 8) | buffer.read_u8(start + i) as u64
+        })
+    };
+
+    // masks and the standard feature count
+    let mut offset = rreq.offset + 1;
+    if offset + 2 * mask_length + 2 > end {
+        return Ok(None);
+    }
+    let buffer = ri.read(offset, 2 * mask_length + 2)?;
+    let fully_understand_mask = read_mask(&buffer, 0);
+    let decode_completely_mask = read_mask(&buffer, mask_length);
+    let standard_count = buffer.read_u16_be(2 * mask_length) as usize;
+    offset += buffer.len();
+
+    // standard features and the vendor feature count
+    let standard_size = standard_count * (2 + mask_length);
+    if offset + standard_size + 2 > end {
+        return Ok(None);
+    }
+    let buffer = ri.read(offset, standard_size + 2)?;
+    let standard_features = (0..standard_count)
+        .map(|i| Jpeg2000StandardFeature {
+            id: buffer.read_u16_be(i * (2 + mask_length)),
+            mask: read_mask(&buffer, i * (2 + mask_length) + 2),
+        })
+        .collect();
+    let vendor_count = buffer.read_u16_be(standard_size) as usize;
+    offset += buffer.len();
+
+    let vendor_size = vendor_count * (16 + mask_length);
+    if offset + vendor_size > end {
+        return Ok(None);
+    }
+    let buffer = ri.read(offset, vendor_size)?;
+    let vendor_features = (0..vendor_count)
+        .map(|i| {
+            let uuid = buffer.piece(i * (16 + mask_length), 16);
+            uuid.iter().map(|b| format!("{:02x}", b)).collect()
+        })
+        .collect();
+    if offset + vendor_size != end {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+
+    Ok(Some(Jpeg2000ReaderRequirements {
+        fully_understand_mask,
+        decode_completely_mask,
+        standard_features,
+        vendor_features,
+    }))
+}
+
+// A raw codestream, or the first codestream of a JP2/JPH/JPX file
+pub fn read_jpeg2000_details<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+) -> ImageInfoResult<Jpeg2000Details>
+where
+    R: BufRead + Seek,
+{
+    if length >= 2 && ri.read(0, 2)?.cmp(0, 2, b"\xFF\x4F") {
+        return read_jpeg2000_code_stream(ri, length);
+    }
+
+    let boxes = read_boxes(ri, 0, length)?;
+    let (offset, code_stream_length) = *find_code_streams(ri, &boxes)?
+        .first()
+        .ok_or(ImageInfoError::UnrecognizedFormat)?;
+    let mut details = {
+        let mut sub = ri.sub_interface(offset, code_stream_length);
+        read_jpeg2000_code_stream(&mut sub, code_stream_length)?
+    };
+    if let Some(rreq) = boxes.iter().find(|b| &b.box_type == b"rreq") {
+        details.reader_requirements = read_reader_requirements(ri, rreq)?;
+    }
+    Ok(details)
 }
@@ -1,4 +1,7 @@
+use crate::details::{Jpeg2000Component, Jpeg2000Details};
+use crate::formats::div_ceil;
 use crate::{ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface};
+use std::cmp::min;
 use std::io::{BufRead, Seek};
 
 // https://docs.fileformat.com/image/jp2/
@@ -20,18 +23,137 @@ where
         if length < siz_length as usize + 4 {
             return Err(ImageInfoError::UnrecognizedFormat);
         }
-        return Ok(ImageInfo {
-            format: ImageFormat::J2K,
-            ext: "j2k",
-            full_ext: "j2k",
-            mimetype: "image/j2k",
-            size: ImageSize {
+        // the reference grid, Jpeg2000Details has the size without the
+        // image offset
+        return Ok(ImageInfo::from_format(
+            ImageFormat::J2K,
+            ImageSize {
                 width: buffer.read_u32_be(8) as i64,
                 height: buffer.read_u32_be(12) as i64,
             },
-            entry_sizes: vec![],
-        });
+        ));
     }
 
     Err(ImageInfoError::UnrecognizedFormat)
 }
+
+// Main header markers, from SOC up to the first tile-part (SOT)
+pub fn read_jpeg2000_code_stream<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+) -> ImageInfoResult<Jpeg2000Details>
+where
+    R: BufRead + Seek,
+{
+    if length < 4 || !ri.read(0, 2)?.cmp(0, 2, b"\xFF\x4F") {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+
+    let mut details: Option<Jpeg2000Details> = None;
+    let mut has_part15_capability = false;
+    let mut offset = 2usize;
+    while offset + 4 <= length {
+        let buffer = ri.read(offset, 4)?;
+        let marker = buffer.read_u16_be(0);
+        if marker == 0xFF90 || marker == 0xFF93 || marker == 0xFFD9 {
+            break;
+        }
+        let segment_length = buffer.read_u16_be(2) as usize;
+        if segment_length < 2 || offset + 2 + segment_length > length {
+            return Err(ImageInfoError::UnrecognizedFormat);
+        }
+        let data = ri.read(offset + 4, segment_length - 2)?;
+        match marker {
+            // SIZ
+            0xFF51 if data.len() >= 36 => {
+                let component_count = data.read_u16_be(34) as usize;
+                if data.len() < 36 + component_count * 3 {
+                    return Err(ImageInfoError::UnrecognizedFormat);
+                }
+                let width = data.read_u32_be(2);
+                let height = data.read_u32_be(6);
+                let x_offset = data.read_u32_be(10);
+                let y_offset = data.read_u32_be(14);
+                let tile_width = data.read_u32_be(18);
+                let tile_height = data.read_u32_be(22);
+                let tile_x_offset = data.read_u32_be(26);
+                let tile_y_offset = data.read_u32_be(30);
+                if tile_width == 0 || tile_height == 0 || width <= x_offset || height <= y_offset {
+                    return Err(ImageInfoError::UnrecognizedFormat);
+                }
+                details = Some(Jpeg2000Details {
+                    capabilities: data.read_u16_be(0),
+                    is_htj2k: false,
+                    size: ImageSize {
+                        width: (width - x_offset) as i64,
+                        height: (height - y_offset) as i64,
+                    },
+                    image_offset: (x_offset, y_offset),
+                    tile_size: ImageSize {
+                        width: tile_width as i64,
+                        height: tile_height as i64,
+                    },
+                    tile_offset: (tile_x_offset, tile_y_offset),
+                    tile_columns: div_ceil(
+                        width.saturating_sub(tile_x_offset) as u64,
+                        tile_width as u64,
+                    ) as u32,
+                    tile_rows: div_ceil(
+                        height.saturating_sub(tile_y_offset) as u64,
+                        tile_height as u64,
+                    ) as u32,
+                    components: (0..component_count)
+                        .map(|i| {
+                            let precision = data.read_u8(36 + i * 3);
+                            Jpeg2000Component {
+                                bit_depth: (precision & 0x7F) + 1,
+                                signed: precision & 0x80 != 0,
+                                horizontal_separation: data.read_u8(37 + i * 3),
+                                vertical_separation: data.read_u8(38 + i * 3),
+                            }
+                        })
+                        .collect(),
+                    decomposition_levels: 0,
+                    reversible: false,
+                    layers: 0,
+                    progression_order: 0,
+                    resolutions: vec![],
+                    reader_requirements: None,
+                });
+            }
+            // CAP, Pcap bit 15 (counted from the MSB) is Part 15, HTJ2K
+            0xFF50 if data.len() >= 4 => {
+                has_part15_capability = data.read_u32_be(0) & (1 << (32 - 15)) != 0;
+            }
+            // COD
+            0xFF52 if data.len() >= 10 => {
+                if let Some(details) = details.as_mut() {
+                    details.progression_order = data.read_u8(1);
+                    details.layers = data.read_u16_be(2);
+                    details.decomposition_levels = data.read_u8(5);
+                    // 1 is the 5-3 reversible filter, 0 the 9-7 irreversible one
+                    details.reversible = data.read_u8(9) == 1;
+                }
+            }
+            _ => {}
+        }
+        offset += 2 + segment_length;
+    }
+
+    let mut details = details.ok_or(ImageInfoError::UnrecognizedFormat)?;
+    // Rsiz bit 14 marks a Part 15 codestream, described by CAP
+    details.is_htj2k = details.capabilities & 0x4000 != 0 || has_part15_capability;
+
+    // reference grid divided by 2^r, as in the decoder
+    let (x_offset, y_offset) = details.image_offset;
+    let width = x_offset as u64 + details.size.width as u64;
+    let height = y_offset as u64 + details.size.height as u64;
+    for level in 0..=min(details.decomposition_levels, 32) as u32 {
+        let divisor = 1u64 << level;
+        details.resolutions.push(ImageSize {
+            width: (div_ceil(width, divisor) - div_ceil(x_offset as u64, divisor)) as i64,
+            height: (div_ceil(height, divisor) - div_ceil(y_offset as u64, divisor)) as i64,
+        });
+    }
+    Ok(details)
+}
@@ -1,6 +1,15 @@
+use crate::details::{JpegCodingProcess, JpegComponent, JpegDetails, JpegQuantizationTable};
+use crate::exif::read_orientation;
+use crate::RawBuffer;
 use crate::{ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface};
+use std::cmp::min;
 use std::io::{BufRead, Seek};
 
+// 0xC4 (DHT), 0xC8 (JPG) and 0xCC (DAC) share the range but are no frames
+fn is_sof(marker: u8) -> bool {
+    (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC)
+}
+
 // https://www.fileformat.info/format/jpeg/corion.htm
 pub fn try_jpg<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<ImageInfo>
 where
@@ -14,17 +23,13 @@ where
         return Err(ImageInfoError::UnrecognizedFormat);
     }
 
-    let mut ret = ImageInfo {
-        format: ImageFormat::JPEG,
-        ext: "jpg",
-        full_ext: "jpeg",
-        mimetype: "image/jpeg",
-        size: ImageSize {
+    let mut ret = ImageInfo::from_format(
+        ImageFormat::JPEG,
+        ImageSize {
             width: 0,
             height: 0,
         },
-        entry_sizes: vec![],
-    };
+    );
 
     let mut orientation = 1u16;
     let mut offset = 2usize;
@@ -42,52 +47,17 @@ where
             if offset + section_size + 2 > length {
                 return Err(ImageInfoError::UnrecognizedFormat);
             }
-            let exif_buffer = ri.read(offset, section_size + 2)?;
-            if exif_buffer.cmp(4, 5, b"Exif\x00") {
-                let big_endian = !exif_buffer.cmp(10, 1, b"I");
-                let first_ifd_offset = if big_endian {
-                    exif_buffer.read_u32_be(14)
-                } else {
-                    exif_buffer.read_u32_le(14)
-                };
-                if first_ifd_offset < 8
-                    || (first_ifd_offset as u64) + 12u64 > (section_size as u64 + 2u64)
-                {
-                    return Err(ImageInfoError::UnrecognizedFormat);
-                }
-                let ifd_main_entries_count = if big_endian {
-                    exif_buffer.read_u16_be(first_ifd_offset as usize + 10)
-                } else {
-                    exif_buffer.read_u16_le(first_ifd_offset as usize + 10)
-                };
-                for i in 0..ifd_main_entries_count {
-                    let entry_offset = first_ifd_offset as usize + 12 + 12 * i as usize;
-                    if entry_offset + 12 > section_size + 2 {
-                        return Err(ImageInfoError::UnrecognizedFormat);
-                    }
-                    let tag = if big_endian {
-                        exif_buffer.read_u16_be(entry_offset)
-                    } else {
-                        exif_buffer.read_u16_le(entry_offset)
-                    };
-                    // Orientation Tag
-                    if tag == 274 {
-                        orientation = if big_endian {
-                            exif_buffer.read_u16_be(entry_offset + 8)
-                        } else {
-                            exif_buffer.read_u16_le(entry_offset + 8)
-                        };
-                    }
-                }
+            if buffer.cmp(4, 5, b"Exif\x00") {
+                orientation = read_orientation(ri, offset + 10, offset + section_size + 2)?
+                    .unwrap_or(orientation);
             }
             offset += section_size + 2;
             continue;
         }
 
-        // 0xFFC0 is baseline standard (SOF0)
-        // 0xFFC1 is baseline optimized (SOF1)
-        // 0xFFC2 is progressive (SOF2)
-        if buffer.cmp_any_of(0, 2, vec![b"\xFF\xC0", b"\xFF\xC1", b"\xFF\xC2"]) {
+        // SOF0 to SOF15, or DHP that carries the full size of hierarchical images
+        let marker = buffer.read_u8(1);
+        if is_sof(marker) || marker == 0xDE {
             let mut size = ImageSize {
                 width: buffer.read_u16_be(7) as i64,
                 height: buffer.read_u16_be(5) as i64,
@@ -103,3 +73,271 @@ where
 
     Err(ImageInfoError::UnrecognizedFormat)
 }
+
+// Natural (row-major) index of each zigzag position
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+// ITU-T T.81 Annex K tables, the base of the libjpeg quality scaling
+const STD_LUMINANCE_TABLE: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56,
+    14, 17, 22, 29, 51, 87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113,
+    92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+const STD_CHROMINANCE_TABLE: [u16; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99, 18, 21, 26, 66, 99, 99, 99, 99, 24, 26, 56, 99, 99, 99, 99, 99,
+    47, 66, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+];
+
+fn read_dqt(data: &RawBuffer) -> ImageInfoResult<Vec<JpegQuantizationTable>> {
+    let mut tables = vec![];
+    let mut offset = 0usize;
+    while offset < data.len() {
+        let precision = data.read_u8(offset) >> 4;
+        let id = data.read_u8(offset) & 0x0F;
+        let value_size = if precision == 0 { 1 } else { 2 };
+        if offset + 1 + 64 * value_size > data.len() {
+            return Err(ImageInfoError::UnrecognizedFormat);
+        }
+        let mut values = vec![0u16; 64];
+        for (k, &natural) in ZIGZAG.iter().enumerate() {
+            let value_offset = offset + 1 + k * value_size;
+            values[natural] = if precision == 0 {
+                data.read_u8(value_offset) as u16
+            } else {
+                data.read_u16_be(value_offset)
+            };
+        }
+        tables.push(JpegQuantizationTable {
+            id,
+            precision,
+            values,
+        });
+        offset += 1 + 64 * value_size;
+    }
+    Ok(tables)
+}
+
+// Tables libjpeg writes for the given quality, see jpeg_quality_scaling()
+fn scaled_table(std_table: &[u16; 64], quality: u32) -> impl Iterator<Item = u16> + '_ {
+    let scale = if quality < 50 {
+        5000 / quality
+    } else {
+        200 - quality * 2
+    };
+    std_table
+        .iter()
+        .map(move |&value| ((value as u32 * scale + 50) / 100).clamp(1, 255) as u16)
+}
+
+// The libjpeg quality whose tables are the closest to the luminance table
+// (first component) and the chrominance table (second component)
+fn estimate_quality(components: &[JpegComponent], tables: &[JpegQuantizationTable]) -> Option<u8> {
+    let find_table = |component: Option<&JpegComponent>| {
+        component.and_then(|c| tables.iter().find(|t| t.id == c.quantization_table))
+    };
+    let luminance = find_table(components.first())?;
+    let chrominance = find_table(components.get(1)).filter(|t| t.id != luminance.id);
+
+    let distance = |table: &JpegQuantizationTable, std_table, quality| -> u64 {
+        table
+            .values
+            .iter()
+            .zip(scaled_table(std_table, quality))
+            .map(|(&a, b)| (a as i64 - b as i64).unsigned_abs())
+            .sum()
+    };
+    (1..=100u32)
+        .min_by_key(|&quality| {
+            distance(luminance, &STD_LUMINANCE_TABLE, quality)
+                + chrominance.map_or(0, |t| distance(t, &STD_CHROMINANCE_TABLE, quality))
+        })
+        .map(|quality| quality as u8)
+}
+
+// Frame header and the tables/markers preceding the first scan
+//
+// https://www.w3.org/Graphics/JPEG/itu-t81.pdf
+pub fn read_jpeg_details<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+) -> ImageInfoResult<JpegDetails>
+where
+    R: BufRead + Seek,
+{
+    if length < 2 || !ri.read(0, 2)?.cmp(0, 2, b"\xFF\xD8") {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+
+    let mut details: Option<JpegDetails> = None;
+    let mut restart_interval = None;
+    let mut jfif_version = None;
+    let mut adobe_transform = None;
+    let mut quantization_tables = vec![];
+    let mut offset = 2usize;
+    while offset + 4 <= length {
+        let buffer = ri.read(offset, 4)?;
+        if !buffer.cmp(0, 1, b"\xFF") {
+            // skip garbage bytes
+            offset += 1;
+            continue;
+        }
+        let marker = buffer.read_u8(1);
+        // fill bytes and markers without a length
+        if marker == 0xFF {
+            offset += 1;
+            continue;
+        }
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            offset += 2;
+            continue;
+        }
+        // Start of scan or end of image, the frame is complete
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        let section_size = buffer.read_u16_be(2) as usize;
+        if section_size < 2 || offset + section_size + 2 > length {
+            return Err(ImageInfoError::UnrecognizedFormat);
+        }
+        let data = ri.read(offset + 4, section_size - 2)?;
+
+        if is_sof(marker) && details.is_none() {
+            if data.len() < 6 {
+                return Err(ImageInfoError::UnrecognizedFormat);
+            }
+            let component_count = data.read_u8(5) as usize;
+            if data.len() < 6 + component_count * 3 {
+                return Err(ImageInfoError::UnrecognizedFormat);
+            }
+            let components = (0..component_count)
+                .map(|i| JpegComponent {
+                    id: data.read_u8(6 + i * 3),
+                    horizontal_sampling: data.read_u8(7 + i * 3) >> 4,
+                    vertical_sampling: data.read_u8(7 + i * 3) & 0x0F,
+                    quantization_table: data.read_u8(8 + i * 3),
+                })
+                .collect();
+            // the low two bits select the process, bit 2 differential and bit 3 arithmetic
+            let coding_process = match marker & 0x03 {
+                0 if marker == 0xC0 => JpegCodingProcess::Baseline,
+                0 | 1 => JpegCodingProcess::ExtendedSequential,
+                2 => JpegCodingProcess::Progressive,
+                _ => JpegCodingProcess::Lossless,
+            };
+            details = Some(JpegDetails {
+                sof_marker: marker,
+                coding_process,
+                arithmetic_coding: marker & 0x08 != 0,
+                differential: marker & 0x04 != 0,
+                precision: data.read_u8(0),
+                components,
+                restart_interval: None,
+                jfif_version: None,
+                adobe_transform: None,
+                quantization_tables: vec![],
+                estimated_quality: None,
+            });
+        } else if marker == 0xDB {
+            for table in read_dqt(&data)? {
+                // a later table with the same id replaces the earlier one
+                quantization_tables.retain(|t: &JpegQuantizationTable| t.id != table.id);
+                quantization_tables.push(table);
+            }
+        } else if marker == 0xDD && data.len() >= 2 {
+            let interval = data.read_u16_be(0);
+            restart_interval = if interval > 0 { Some(interval) } else { None };
+        } else if marker == 0xE0 && data.len() >= 7 && data.cmp(0, 5, b"JFIF\x00") {
+            jfif_version = Some(format!("{}.{:02}", data.read_u8(5), data.read_u8(6)));
+        } else if marker == 0xEE && data.len() >= 12 && data.cmp(0, 5, b"Adobe") {
+            adobe_transform = Some(data.read_u8(11));
+        }
+        offset += section_size + 2;
+    }
+
+    match details {
+        Some(mut details) => {
+            details.restart_interval = restart_interval;
+            details.jfif_version = jfif_version;
+            details.adobe_transform = adobe_transform;
+            if details.coding_process != JpegCodingProcess::Lossless {
+                details.estimated_quality =
+                    estimate_quality(&details.components, &quantization_tables);
+            }
+            details.quantization_tables = quantization_tables;
+            Ok(details)
+        }
+        None => Err(ImageInfoError::UnrecognizedFormat),
+    }
+}
+
+// Offset just past the EOI marker, walking the segments and the entropy-coded
+// data of every scan. None if the data ends before EOI.
+pub fn find_jpeg_end<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<Option<usize>>
+where
+    R: BufRead + Seek,
+{
+    if length < 2 || !ri.read(0, 2)?.cmp(0, 2, b"\xFF\xD8") {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+
+    let piece = 4096usize;
+    let mut offset = 2usize;
+    while offset + 2 <= length {
+        let buffer = ri.read(offset, 2)?;
+        if !buffer.cmp(0, 1, b"\xFF") {
+            // skip garbage bytes
+            offset += 1;
+            continue;
+        }
+        let marker = buffer.read_u8(1);
+        if marker == 0xFF {
+            offset += 1;
+            continue;
+        }
+        if marker == 0xD9 {
+            return Ok(Some(offset + 2));
+        }
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            offset += 2;
+            continue;
+        }
+        if offset + 4 > length {
+            break;
+        }
+        let section_size = ri.read(offset + 2, 2)?.read_u16_be(0) as usize;
+        offset += section_size + 2;
+        if marker != 0xDA {
+            continue;
+        }
+
+        // entropy-coded data runs up to the next marker other than a stuffed
+        // zero or a restart marker
+        'scan: while offset < length {
+            let buffer = ri.read(offset, min(piece, length - offset))?;
+            for i in 0..buffer.len() {
+                if buffer.read_u8(i) != 0xFF {
+                    continue;
+                }
+                if offset + i + 1 >= length {
+                    return Ok(None);
+                }
+                let next = if i + 1 < buffer.len() {
+                    buffer.read_u8(i + 1)
+                } else {
+                    ri.read(offset + i + 1, 1)?.read_u8(0)
+                };
+                if next != 0x00 && !(0xD0..=0xD7).contains(&next) {
+                    offset += i;
+                    break 'scan;
+                }
+            }
+            offset += buffer.len();
+        }
+    }
+    Ok(None)
+}
@@ -14,15 +14,29 @@ where
         return Err(ImageInfoError::UnrecognizedFormat);
     }
 
-    Ok(ImageInfo {
-        format: ImageFormat::KTX,
-        ext: "ktx",
-        full_ext: "ktx",
-        mimetype: "image/ktx",
-        size: ImageSize {
+    Ok(ImageInfo::from_format(
+        ImageFormat::KTX,
+        ImageSize {
             width: buffer.read_u32_le(36) as i64,
             height: buffer.read_u32_le(40) as i64,
         },
-        entry_sizes: vec![],
-    })
+    ))
+}
+
+// Mipmap levels, then the number of faces times array elements
+pub fn read_ktx_levels<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<(u32, u32)>
+where
+    R: BufRead + Seek,
+{
+    if length < 64 {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    let buffer = ri.read(0, 64)?;
+    // written as 0x04030201 in the file's byte order
+    let little_endian = buffer.read_u32_le(12) == 0x04030201;
+    let array_elements = buffer.read_u32(48, little_endian).max(1);
+    let faces = buffer.read_u32(52, little_endian).max(1);
+    // 0 asks the loader to generate a full mipmap chain
+    let levels = buffer.read_u32(56, little_endian);
+    Ok((levels, faces.saturating_mul(array_elements)))
 }
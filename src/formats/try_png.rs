@@ -1,3 +1,4 @@
+use crate::details::{PngChromaticities, PngChunk, PngDetails, PngText, PngTime};
 use crate::{ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface};
 use std::cmp::min;
 use std::io::{BufRead, Seek};
@@ -16,30 +17,135 @@ where
     }
 
     if buffer.cmp(12, 4, b"IHDR") {
-        return Ok(ImageInfo {
-            format: ImageFormat::PNG,
-            ext: "png",
-            full_ext: "png",
-            mimetype: "image/png",
-            size: ImageSize {
+        return Ok(ImageInfo::from_format(
+            ImageFormat::PNG,
+            ImageSize {
                 width: buffer.read_u32_be(16) as i64,
                 height: buffer.read_u32_be(20) as i64,
             },
-            entry_sizes: vec![],
-        });
+        ));
     } else if buffer.cmp(12, 4, b"CgBI") && buffer.len() >= 40 && buffer.cmp(28, 4, b"IHDR") {
-        return Ok(ImageInfo {
-            format: ImageFormat::PNG,
-            ext: "png",
-            full_ext: "png",
-            mimetype: "image/png",
-            size: ImageSize {
+        return Ok(ImageInfo::from_format(
+            ImageFormat::PNG,
+            ImageSize {
                 width: buffer.read_u32_be(32) as i64,
                 height: buffer.read_u32_be(36) as i64,
             },
-            entry_sizes: vec![],
-        });
+        ));
     }
 
     Err(ImageInfoError::UnrecognizedFormat)
 }
+
+// Data of the small chunks worth decoding, text chunks only up to the keyword.
+// Fixed size chunks of another length are corrupt and not read at all.
+fn chunk_data_size(chunk_type: &str, length: usize) -> usize {
+    match (chunk_type, length) {
+        ("IHDR", 13) | ("gAMA", 4) | ("cHRM", 32) | ("tIME", 7) => length,
+        // one byte per channel of the color type
+        ("sBIT", 1..=4) => length,
+        // keyword is 1-79 bytes followed by a null separator
+        ("tEXt", _) | ("zTXt", _) | ("iTXt", _) => min(length, 80),
+        _ => 0,
+    }
+}
+
+// Walks the chunk stream up to IEND, stopping quietly at a truncated chunk
+pub fn read_png_details<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<PngDetails>
+where
+    R: BufRead + Seek,
+{
+    let mut details = PngDetails {
+        bit_depth: 0,
+        color_type: 0,
+        interlace_method: 0,
+        chunks: vec![],
+        gamma: None,
+        chromaticities: None,
+        significant_bits: None,
+        texts: vec![],
+        last_modified: None,
+        has_transparency: false,
+        has_iend: false,
+    };
+    let mut has_ihdr = false;
+
+    let mut offset = 8usize;
+    while offset + 12 <= length {
+        let buffer = ri.read(offset, 8)?;
+        let chunk_length = buffer.read_u32_be(0) as usize;
+        let chunk_type = buffer.read_str(4, 4);
+        if offset + 12 + chunk_length > length {
+            break;
+        }
+        details.chunks.push(PngChunk {
+            chunk_type: chunk_type.clone(),
+            offset: offset as u64,
+            length: chunk_length as u32,
+        });
+
+        let data = ri.read(offset + 8, chunk_data_size(&chunk_type, chunk_length))?;
+        match chunk_type.as_str() {
+            "IHDR" if data.len() == 13 => {
+                details.bit_depth = data.read_u8(8);
+                details.color_type = data.read_u8(9);
+                details.interlace_method = data.read_u8(12);
+                has_ihdr = true;
+            }
+            "gAMA" if data.len() == 4 => {
+                details.gamma = Some(data.read_u32_be(0) as f64 / 100000.0);
+            }
+            "cHRM" if data.len() == 32 => {
+                let point = |index: usize| {
+                    (
+                        data.read_u32_be(index * 8) as f64 / 100000.0,
+                        data.read_u32_be(index * 8 + 4) as f64 / 100000.0,
+                    )
+                };
+                details.chromaticities = Some(PngChromaticities {
+                    white_point: point(0),
+                    red: point(1),
+                    green: point(2),
+                    blue: point(3),
+                });
+            }
+            "sBIT" if data.len() > 0 => {
+                details.significant_bits = Some(data.piece(0, data.len()).to_vec())
+            }
+            "tIME" if data.len() == 7 => {
+                details.last_modified = Some(PngTime {
+                    year: data.read_u16_be(0),
+                    month: data.read_u8(2),
+                    day: data.read_u8(3),
+                    hour: data.read_u8(4),
+                    minute: data.read_u8(5),
+                    second: data.read_u8(6),
+                });
+            }
+            "tEXt" | "zTXt" | "iTXt" => {
+                let keyword = data
+                    .piece(0, data.len())
+                    .split(|&b| b == 0)
+                    .next()
+                    .unwrap_or(&[]);
+                details.texts.push(PngText {
+                    chunk_type: chunk_type.clone(),
+                    // keywords are Latin-1
+                    keyword: keyword.iter().map(|&b| b as char).collect(),
+                });
+            }
+            "tRNS" => details.has_transparency = true,
+            "IEND" => {
+                details.has_iend = true;
+                break;
+            }
+            _ => {}
+        }
+        offset += 12 + chunk_length;
+    }
+
+    if !has_ihdr {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    Ok(details)
+}
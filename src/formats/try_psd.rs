@@ -1,6 +1,8 @@
+use crate::details::{PsdColorMode, PsdDetails, PsdImageResource};
 use crate::{ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface};
 use std::io::{BufRead, Seek};
 
+// https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/
 pub fn try_psd<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<ImageInfo>
 where
     R: BufRead + Seek,
@@ -9,19 +11,198 @@ where
         return Err(ImageInfoError::UnrecognizedFormat);
     }
     let buffer = ri.read(0, 22)?;
-    if !buffer.cmp(0, 6, b"8BPS\x00\x01") {
-        return Err(ImageInfoError::UnrecognizedFormat);
-    }
-
-    Ok(ImageInfo {
-        format: ImageFormat::PSD,
-        ext: "psd",
-        full_ext: "psd",
-        mimetype: "image/psd",
-        size: ImageSize {
+    let mut ret = ImageInfo::from_format(
+        ImageFormat::PSD,
+        ImageSize {
             width: buffer.read_u32_be(18) as i64,
             height: buffer.read_u32_be(14) as i64,
         },
-        entry_sizes: vec![],
+    );
+    if buffer.cmp(0, 6, b"8BPS\x00\x01") {
+        Ok(ret)
+    }
+    // Large Document Format, same header
+    else if buffer.cmp(0, 6, b"8BPS\x00\x02") {
+        ret.ext = "psb";
+        ret.full_ext = "psb";
+        Ok(ret)
+    } else {
+        Err(ImageInfoError::UnrecognizedFormat)
+    }
+}
+
+// Offset of the image resources section length, after the header and the
+// color mode data section
+fn image_resources_offset<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<usize>
+where
+    R: BufRead + Seek,
+{
+    if length < 30 {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    let color_mode_length = ri.read(26, 4)?.read_u32_be(0) as usize;
+    let offset = 30usize.saturating_add(color_mode_length);
+    if offset + 4 > length {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    Ok(offset)
+}
+
+// 8BIM resource blocks, up to the first malformed one
+pub fn read_psd_resources<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+) -> ImageInfoResult<Vec<PsdImageResource>>
+where
+    R: BufRead + Seek,
+{
+    let mut offset = image_resources_offset(ri, length)?;
+    let resources_length = ri.read(offset, 4)?.read_u32_be(0) as usize;
+    offset += 4;
+    let end = offset.saturating_add(resources_length).min(length);
+
+    let mut resources = vec![];
+    while offset + 12 <= end {
+        let buffer = ri.read(offset, 8)?;
+        if !buffer.cmp(0, 4, b"8BIM") {
+            break;
+        }
+        let id = buffer.read_u16_be(4);
+        // pascal string padded to an even length
+        let name_length = buffer.read_u8(6) as usize;
+        let data_offset = offset + 6 + ((name_length + 2) & !1);
+        if data_offset + 4 > end {
+            break;
+        }
+        let name = ri.read(offset + 7, name_length)?.read_str_all();
+        let data_length = ri.read(data_offset, 4)?.read_u32_be(0) as usize;
+        let data_start = data_offset + 4;
+        if data_start.saturating_add(data_length) > end {
+            break;
+        }
+        resources.push(PsdImageResource {
+            id,
+            name,
+            offset: data_start as u64,
+            length: data_length as u32,
+        });
+        offset = data_start + data_length + (data_length & 1);
+    }
+    Ok(resources)
+}
+
+// Additional layer information keys with a 64-bit length in PSB files
+const PSB_LONG_KEYS: [&[u8]; 13] = [
+    b"LMsk", b"Lr16", b"Lr32", b"Layr", b"Mt16", b"Mt32", b"Mtrn", b"Alph", b"FMsk", b"lnk2",
+    b"FEid", b"FXid", b"PxSD",
+];
+
+// Layer count from the layer info, or from the Lr16/Lr32 block that holds
+// it in 16 and 32-bit documents. Layer records and pixels are not read.
+fn read_layer_count<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    is_psb: bool,
+) -> ImageInfoResult<Option<u16>>
+where
+    R: BufRead + Seek,
+{
+    let length_size = if is_psb { 8 } else { 4 };
+    let read_length = |ri: &mut ReadInterface<R>, offset: usize| -> ImageInfoResult<usize> {
+        let buffer = ri.read(offset, length_size)?;
+        Ok(if is_psb {
+            buffer.read_u64_be(0) as usize
+        } else {
+            buffer.read_u32_be(0) as usize
+        })
+    };
+
+    let offset = image_resources_offset(ri, length)?;
+    let resources_length = ri.read(offset, 4)?.read_u32_be(0) as usize;
+    let section_offset = (offset + 4).saturating_add(resources_length);
+    if section_offset.saturating_add(length_size) > length {
+        return Ok(None);
+    }
+    let section_length = read_length(ri, section_offset)?;
+    if section_length == 0 {
+        return Ok(Some(0));
+    }
+    let section_end = (section_offset + length_size)
+        .saturating_add(section_length)
+        .min(length);
+
+    // layer info, its length is padded, then the count as a signed 16-bit value
+    let info_offset = section_offset + length_size;
+    if info_offset + length_size > section_end {
+        return Ok(None);
+    }
+    let info_length = read_length(ri, info_offset)?;
+    if info_length >= 2 {
+        if info_offset + length_size + 2 > section_end {
+            return Ok(None);
+        }
+        let count = ri.read(info_offset + length_size, 2)?.read_u16_be(0) as i16;
+        return Ok(Some(count.unsigned_abs()));
+    }
+
+    // global layer mask info, then additional layer information blocks
+    let mask_offset = (info_offset + length_size).saturating_add(info_length);
+    if mask_offset.saturating_add(4) > section_end {
+        return Ok(Some(0));
+    }
+    let mask_length = ri.read(mask_offset, 4)?.read_u32_be(0) as usize;
+    let mut offset = (mask_offset + 4).saturating_add(mask_length);
+    while offset.saturating_add(8 + length_size) <= section_end {
+        let buffer = ri.read(offset, 8)?;
+        if !buffer.cmp_any_of(0, 4, vec![b"8BIM", b"8B64"]) {
+            break;
+        }
+        let key = buffer.piece(4, 4);
+        let (block_length, data_offset) = if is_psb && PSB_LONG_KEYS.contains(&key) {
+            (read_length(ri, offset + 8)?, offset + 16)
+        } else {
+            (ri.read(offset + 8, 4)?.read_u32_be(0) as usize, offset + 12)
+        };
+        if key == b"Lr16" || key == b"Lr32" || key == b"Layr" {
+            if block_length < 2 || data_offset + 2 > section_end {
+                return Ok(None);
+            }
+            let count = ri.read(data_offset, 2)?.read_u16_be(0) as i16;
+            return Ok(Some(count.unsigned_abs()));
+        }
+        // blocks are padded to 4 bytes
+        offset = data_offset.saturating_add(block_length.saturating_add(3) & !3);
+    }
+    Ok(Some(0))
+}
+
+pub fn read_psd_details<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<PsdDetails>
+where
+    R: BufRead + Seek,
+{
+    if length < 26 {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    let buffer = ri.read(0, 26)?;
+    let version = buffer.read_u16_be(4);
+    let color_mode = match buffer.read_u16_be(24) {
+        0 => PsdColorMode::Bitmap,
+        1 => PsdColorMode::Grayscale,
+        2 => PsdColorMode::Indexed,
+        3 => PsdColorMode::Rgb,
+        4 => PsdColorMode::Cmyk,
+        7 => PsdColorMode::Multichannel,
+        8 => PsdColorMode::Duotone,
+        9 => PsdColorMode::Lab,
+        mode => PsdColorMode::Unknown(mode),
+    };
+
+    Ok(PsdDetails {
+        version,
+        channels: buffer.read_u16_be(12),
+        depth: buffer.read_u16_be(22),
+        color_mode,
+        layer_count: read_layer_count(ri, length, version == 2)?,
+        image_resources: read_psd_resources(ri, length)?,
     })
 }
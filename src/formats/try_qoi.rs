@@ -1,27 +1,54 @@
+use crate::details::{QoiColorspace, QoiDetails};
 use crate::{ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface};
 use std::io::{BufRead, Seek};
 
+// https://qoiformat.org/qoi-specification.pdf
+const QOI_END_MARKER: &[u8] = b"\x00\x00\x00\x00\x00\x00\x00\x01";
+
 pub fn try_qoi<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<ImageInfo>
 where
     R: BufRead + Seek,
 {
-    if length < 12 {
+    if length < 14 {
         return Err(ImageInfoError::UnrecognizedFormat);
     }
-    let buffer = ri.read(0, 12)?;
+    let buffer = ri.read(0, 14)?;
     if !buffer.cmp(0, 4, b"qoif") {
         return Err(ImageInfoError::UnrecognizedFormat);
     }
+    let width = buffer.read_u32_be(4);
+    let height = buffer.read_u32_be(8);
+    // 3 RGB, 4 RGBA; 0 sRGB with linear alpha, 1 all channels linear
+    let channels = buffer.read_u8(12);
+    let colorspace = buffer.read_u8(13);
+    if width == 0 || height == 0 || !(3..=4).contains(&channels) || colorspace > 1 {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
 
-    Ok(ImageInfo {
-        format: ImageFormat::QOI,
-        ext: "qoi",
-        full_ext: "qoi",
-        mimetype: "image/qoi",
-        size: ImageSize {
-            width: buffer.read_u32_be(4) as i64,
-            height: buffer.read_u32_be(8) as i64,
+    Ok(ImageInfo::from_format(
+        ImageFormat::QOI,
+        ImageSize {
+            width: width as i64,
+            height: height as i64,
         },
-        entry_sizes: vec![],
+    ))
+}
+
+pub fn read_qoi_details<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<QoiDetails>
+where
+    R: BufRead + Seek,
+{
+    let buffer = ri.read(0, 14)?;
+    let colorspace = match buffer.read_u8(13) {
+        0 => QoiColorspace::SrgbLinearAlpha,
+        _ => QoiColorspace::Linear,
+    };
+    // the stream can't be shorter than the header and the marker
+    let has_end_marker = length >= 22 && ri.read(length - 8, 8)?.cmp(0, 8, QOI_END_MARKER);
+
+    Ok(QoiDetails {
+        channels: buffer.read_u8(12),
+        colorspace,
+        has_end_marker,
     })
 }
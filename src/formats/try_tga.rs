@@ -1,7 +1,85 @@
+use crate::details::{TgaColorMap, TgaDetails, TgaExtension, TgaOrigin};
+use crate::formats::div_ceil;
 use crate::{ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface};
 use std::io::{BufRead, Seek};
 
-// TODO Not rigorous enough, keep it as last detector
+const TGA_SIGNATURE: &[u8; 18] = b"TRUEVISION-XFILE.\x00";
+const EXTENSION_AREA_SIZE: usize = 495;
+
+// Whether the file ends with the TGA 2.0 footer
+fn has_footer<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<bool>
+where
+    R: BufRead + Seek,
+{
+    Ok(length >= 18 + 26 && ri.read(length - 18, 18)?.cmp(0, 18, TGA_SIGNATURE))
+}
+
+// The fields every TGA header must get right, footer or not
+fn has_valid_fields(header: &[u8]) -> bool {
+    let color_map_type = header[1];
+    let image_type = header[2];
+    let color_map_length = u16::from_le_bytes([header[5], header[6]]);
+    let color_map_entry_size = header[7];
+    let width = u16::from_le_bytes([header[12], header[13]]);
+    let height = u16::from_le_bytes([header[14], header[15]]);
+    let pixel_depth = header[16];
+    let descriptor = header[17];
+
+    let valid_depth = match image_type {
+        // color-mapped
+        1 | 9 | 32 | 33 => color_map_type == 1 && (pixel_depth == 8 || pixel_depth == 16),
+        // true-color, a color map may be present but unused
+        2 | 10 => color_map_type <= 1 && [15, 16, 24, 32].contains(&pixel_depth),
+        // grayscale
+        3 | 11 => color_map_type == 0 && (pixel_depth == 8 || pixel_depth == 16),
+        _ => false,
+    };
+    let alpha_bits = descriptor & 0x0F;
+    // interleaving bits are obsolete and must be zero
+    if !valid_depth || descriptor & 0xC0 != 0 || alpha_bits >= pixel_depth {
+        return false;
+    }
+    if width == 0 || height == 0 {
+        return false;
+    }
+    if color_map_type == 1 {
+        color_map_length != 0 && [15, 16, 24, 32].contains(&color_map_entry_size)
+    } else {
+        header[3..8].iter().all(|&b| b == 0)
+    }
+}
+
+// Cross-checks the header fields and the data size, so that a random binary
+// is not taken for a headerless TGA
+pub fn is_valid_tga_header(header: &[u8], length: usize) -> bool {
+    if !has_valid_fields(header) {
+        return false;
+    }
+    let id_length = header[0] as u64;
+    let color_map_type = header[1];
+    let image_type = header[2];
+    let color_map_length = u16::from_le_bytes([header[5], header[6]]) as u64;
+    let color_map_entry_size = header[7] as u64;
+    let width = u16::from_le_bytes([header[12], header[13]]) as u64;
+    let height = u16::from_le_bytes([header[14], header[15]]) as u64;
+    let pixel_depth = header[16] as u64;
+
+    let color_map_size = if color_map_type == 1 {
+        color_map_length * div_ceil(color_map_entry_size, 8)
+    } else {
+        0
+    };
+    let pixel_size = div_ceil(pixel_depth, 8);
+    let pixel_count = width * height;
+    let data_size = if image_type <= 3 {
+        pixel_count * pixel_size
+    } else {
+        // smallest RLE stream: run-length packets of 128 pixels
+        div_ceil(pixel_count, 128) * (1 + pixel_size)
+    };
+    18 + id_length + color_map_size + data_size <= length as u64
+}
+
 // https://www.fileformat.info/format/tga/corion.htm
 pub fn try_tga<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<ImageInfo>
 where
@@ -10,69 +88,125 @@ where
     if length < 18 {
         return Err(ImageInfoError::UnrecognizedFormat);
     }
-    let buffer = ri.read(length - 18, 18)?;
-    if buffer.cmp(0, 18, b"TRUEVISION-XFILE.\x00") {
-        if length < 18 + 16 {
-            return Err(ImageInfoError::UnrecognizedFormat);
-        }
-        let buffer = ri.read(0, 18)?;
-        return Ok(ImageInfo {
-            format: ImageFormat::TGA,
-            ext: "tga",
-            full_ext: "tga",
-            mimetype: "image/tga",
-            size: ImageSize {
-                width: buffer.read_u16_le(12) as i64,
-                height: buffer.read_u16_le(14) as i64,
-            },
-            entry_sizes: vec![],
-        });
-    }
-
+    let has_footer = has_footer(ri, length)?;
     let buffer = ri.read(0, 18)?;
-    let id_len = buffer.read_u8(0) as usize;
-    if length < id_len + 18 {
+    let header = buffer.piece(0, 18);
+    // the footer vouches for the format, not for the data size
+    let valid = if has_footer {
+        has_valid_fields(header)
+    } else {
+        is_valid_tga_header(header, length)
+    };
+    if !valid {
         return Err(ImageInfoError::UnrecognizedFormat);
     }
-    let color_map_type = buffer.read_u8(1);
-    let image_type = buffer.read_u8(2);
-    let first_color_map_entry_index = buffer.read_u16_le(3);
-    let color_map_length = buffer.read_u16_le(5);
-    let color_map_entry_size = buffer.read_u8(7);
-    let width = buffer.read_u16_le(12) as i64;
-    let height = buffer.read_u16_le(14) as i64;
 
-    if color_map_type == 0 {
-        if (image_type == 0
-            || image_type == 2
-            || image_type == 3
-            || image_type == 10
-            || image_type == 11
-            || image_type == 32
-            || image_type == 33)
-            && first_color_map_entry_index == 0
-            && color_map_length == 0
-            && color_map_entry_size == 0
-        {
-            return Ok(ImageInfo {
-                format: ImageFormat::TGA,
-                ext: "tga",
-                full_ext: "tga",
-                mimetype: "image/tga",
-                size: ImageSize { width, height },
-                entry_sizes: vec![],
-            });
+    Ok(ImageInfo::from_format(
+        ImageFormat::TGA,
+        ImageSize {
+            width: buffer.read_u16_le(12) as i64,
+            height: buffer.read_u16_le(14) as i64,
+        },
+    ))
+}
+
+// Null-terminated ASCII field, trailing spaces removed
+fn read_field(buffer: &[u8]) -> String {
+    let end = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    String::from_utf8_lossy(&buffer[..end])
+        .trim_end()
+        .to_string()
+}
+
+fn read_extension<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+) -> ImageInfoResult<Option<TgaExtension>>
+where
+    R: BufRead + Seek,
+{
+    if !has_footer(ri, length)? {
+        return Ok(None);
+    }
+    let offset = ri.read(length - 26, 4)?.read_u32_le(0) as usize;
+    if offset == 0 || offset + EXTENSION_AREA_SIZE > length - 26 {
+        return Ok(None);
+    }
+    let buffer = ri.read(offset, EXTENSION_AREA_SIZE)?;
+    if buffer.read_u16_le(0) as usize != EXTENSION_AREA_SIZE {
+        return Ok(None);
+    }
+
+    // version times 100, then a letter
+    let version = buffer.read_u16_le(467);
+    let letter = buffer.read_u8(469);
+    let software_version = if version == 0 {
+        None
+    } else if letter.is_ascii_alphabetic() {
+        Some(format!(
+            "{}.{:02}{}",
+            version / 100,
+            version % 100,
+            letter as char
+        ))
+    } else {
+        Some(format!("{}.{:02}", version / 100, version % 100))
+    };
+    let ratio = |offset: usize| {
+        let denominator = buffer.read_u16_le(offset + 2);
+        if denominator == 0 {
+            None
+        } else {
+            Some((buffer.read_u16_le(offset), denominator))
         }
-    } else if color_map_type == 1 && (image_type == 1 || image_type == 9) {
-        return Ok(ImageInfo {
-            format: ImageFormat::TGA,
-            ext: "tga",
-            full_ext: "tga",
-            mimetype: "image/tga",
-            size: ImageSize { width, height },
-            entry_sizes: vec![],
-        });
+    };
+
+    Ok(Some(TgaExtension {
+        author_name: read_field(buffer.piece(2, 41)),
+        software_id: read_field(buffer.piece(426, 41)),
+        software_version,
+        pixel_aspect_ratio: ratio(474),
+        gamma: ratio(478).map(|(numerator, denominator)| numerator as f64 / denominator as f64),
+        attributes_type: buffer.read_u8(494),
+    }))
+}
+
+pub fn read_tga_details<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<TgaDetails>
+where
+    R: BufRead + Seek,
+{
+    if length < 18 {
+        return Err(ImageInfoError::UnrecognizedFormat);
     }
+    let buffer = ri.read(0, 18)?;
+    let id_length = buffer.read_u8(0) as usize;
+    let descriptor = buffer.read_u8(17);
+    let image_id = if id_length > 0 && 18 + id_length <= length {
+        Some(read_field(ri.read(18, id_length)?.piece(0, id_length)))
+    } else {
+        None
+    };
 
-    Err(ImageInfoError::UnrecognizedFormat)
+    Ok(TgaDetails {
+        image_type: buffer.read_u8(2),
+        image_id,
+        color_map: if buffer.read_u8(1) == 1 {
+            Some(TgaColorMap {
+                first_entry: buffer.read_u16_le(3),
+                length: buffer.read_u16_le(5),
+                entry_size: buffer.read_u8(7),
+            })
+        } else {
+            None
+        },
+        pixel_depth: buffer.read_u8(16),
+        alpha_bits: descriptor & 0x0F,
+        origin: match (descriptor >> 4) & 0x03 {
+            0 => TgaOrigin::BottomLeft,
+            1 => TgaOrigin::BottomRight,
+            2 => TgaOrigin::TopLeft,
+            _ => TgaOrigin::TopRight,
+        },
+        extension: read_extension(ri, length)?,
+    })
 }
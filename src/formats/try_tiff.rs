@@ -1,3 +1,5 @@
+use crate::details::TiffPage;
+use crate::ifd::{Ifd, IfdReader};
 use crate::{ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface};
 use std::io::{BufRead, Seek};
 
@@ -6,236 +8,99 @@ pub fn try_tiff<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<
 where
     R: BufRead + Seek,
 {
-    if length < 8 {
-        return Err(ImageInfoError::UnrecognizedFormat);
-    }
-    let buffer = ri.read(0, 8)?;
-    if !buffer.cmp_any_of(0, 2, vec![b"\x49\x49", b"\x4D\x4D"]) {
-        return Err(ImageInfoError::UnrecognizedFormat);
+    let pages = read_tiff_pages(ri, length)?;
+
+    let mut ret = ImageInfo::from_format(
+        ImageFormat::TIFF,
+        ImageSize {
+            width: pages[0].size.width,
+            height: pages[0].size.height,
+        },
+    );
+    // a single page has no entries
+    if pages.len() > 1 {
+        for page in pages {
+            ret.entry_sizes.push(page.size);
+        }
     }
-    let little_endian = buffer.data[0] == 0x49;
-    let tiff_version = if little_endian {
-        buffer.read_u16_le(2)
-    } else {
-        buffer.read_u16_be(2)
-    };
+    Ok(ret)
+}
 
-    if tiff_version == 0x2A {
-        let mut offset = if little_endian {
-            buffer.read_u32_le(4) as usize
-        } else {
-            buffer.read_u32_be(4) as usize
-        };
-        if length < offset + 2 {
-            return Err(ImageInfoError::UnrecognizedFormat);
+// Every IFD of the main chain, the first one must carry a size while later
+// ones without a size are skipped
+pub fn read_tiff_pages<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+) -> ImageInfoResult<Vec<TiffPage>>
+where
+    R: BufRead + Seek,
+{
+    let reader = IfdReader::new(ri, 0, length)?;
+    let chain = reader.read_ifd_chain(ri, reader.first_ifd)?;
+
+    let mut pages = vec![];
+    for (i, ifd) in chain.iter().enumerate() {
+        match read_page(ri, &reader, ifd) {
+            Ok(page) => pages.push(page),
+            Err(ImageInfoError::UnrecognizedFormat) if i > 0 => {}
+            Err(err) => return Err(err),
         }
+    }
+    Ok(pages)
+}
 
-        let buffer = ri.read(offset, 2)?;
-
-        let mum_entry = if little_endian {
-            buffer.read_i16_le(0) as usize
-        } else {
-            buffer.read_i16_be(0) as usize
-        };
-        offset += 2;
-
-        let mut ret = ImageInfo {
-            format: ImageFormat::TIFF,
-            ext: "tif",
-            full_ext: "tiff",
-            mimetype: "image/tiff",
-            size: ImageSize {
-                width: -1,
-                height: -1,
-            },
-            entry_sizes: vec![],
-        };
-
-        let mut i = 0usize;
-        while i < mum_entry && (ret.size.width == -1 || ret.size.height == -1) {
-            if length < offset + 12 {
-                break;
-            }
-            let buffer = ri.read(offset, 12)?;
-
-            let tag = if little_endian {
-                buffer.read_u16_le(0)
-            } else {
-                buffer.read_u16_be(0)
-            };
-
-            let t = if little_endian {
-                buffer.read_u16_le(2)
-            } else {
-                buffer.read_u16_be(2)
-            };
-
-            if tag == 256 {
-                if t == 3 {
-                    ret.size.width = if little_endian {
-                        buffer.read_u16_le(8) as i64
-                    } else {
-                        buffer.read_u16_be(8) as i64
-                    };
-                } else if t == 4 {
-                    ret.size.width = if little_endian {
-                        buffer.read_u32_le(8) as i64
-                    } else {
-                        buffer.read_u32_be(8) as i64
-                    };
-                }
-            } else if tag == 257 {
-                if t == 3 {
-                    ret.size.height = if little_endian {
-                        buffer.read_u16_le(8) as i64
-                    } else {
-                        buffer.read_u16_be(8) as i64
-                    };
-                } else if t == 4 {
-                    ret.size.height = if little_endian {
-                        buffer.read_u32_le(8) as i64
-                    } else {
-                        buffer.read_u32_be(8) as i64
-                    };
-                }
-            }
-
-            i += 1;
-            offset += 12;
-        }
+fn read_page<R>(
+    ri: &mut ReadInterface<R>,
+    reader: &IfdReader,
+    ifd: &Ifd,
+) -> ImageInfoResult<TiffPage>
+where
+    R: BufRead + Seek,
+{
+    let mut size = ImageSize {
+        width: -1,
+        height: -1,
+    };
 
-        if ret.size.width != -1 && ret.size.height != -1 {
-            Ok(ret)
-        } else {
-            Err(ImageInfoError::UnrecognizedFormat)
+    for entry in ifd.entries.iter() {
+        if entry.tag != 256 && entry.tag != 257 {
+            continue;
         }
-    } else if tiff_version == 0x2B {
-        if length < 16 {
-            return Err(ImageInfoError::UnrecognizedFormat);
-        }
-        let byte_size = if little_endian {
-            buffer.read_u16_le(4)
-        } else {
-            buffer.read_u16_be(4)
-        };
-        let zero = if little_endian {
-            buffer.read_u16_le(6)
-        } else {
-            buffer.read_u16_be(6)
-        };
-        if byte_size != 8 || zero != 0 {
-            return Err(ImageInfoError::UnrecognizedFormat);
+        // SHORT, LONG or LONG8
+        if !matches!(entry.field_type, 3 | 4 | 16) {
+            continue;
         }
-        let buffer = ri.read(8, 8)?;
-        let mut offset = if little_endian {
-            buffer.read_u64_le(0)
-        } else {
-            buffer.read_u64_be(0)
-        };
-        if (length as u64) < offset + 8 || offset > (usize::MAX as u64) - 8 {
-            return Err(ImageInfoError::UnrecognizedFormat);
-        }
-        let buffer = ri.read(offset as usize, 8)?;
-        let mum_entry = if little_endian {
-            buffer.read_u64_le(0)
-        } else {
-            buffer.read_u64_be(0)
-        };
-        offset += 8;
-
-        let mut ret = ImageInfo {
-            format: ImageFormat::TIFF,
-            ext: "tif",
-            full_ext: "tiff",
-            mimetype: "image/tiff",
-            size: ImageSize {
-                width: -1,
-                height: -1,
-            },
-            entry_sizes: vec![],
-        };
-
-        let mut i = 0u64;
-        while i < mum_entry && (ret.size.width == -1 || ret.size.height == -1) {
-            if (length as u64) < offset + 20 || offset > (usize::MAX as u64) - 20 {
-                break;
+        if let Some(value) = reader.read_uint(ri, entry, 0)? {
+            if value > (i64::MAX as u64) {
+                // TODO: Size > INT64_MAX is not supported
+                return Err(ImageInfoError::UnrecognizedFormat);
             }
-            let buffer = ri.read(offset as usize, 20)?;
-
-            let tag = if little_endian {
-                buffer.read_u16_le(0)
-            } else {
-                buffer.read_u16_be(0)
-            };
-
-            let t = if little_endian {
-                buffer.read_u16_le(2)
+            if entry.tag == 256 {
+                size.width = value as i64;
             } else {
-                buffer.read_u16_be(2)
-            };
-
-            if tag == 256 {
-                if t == 3 {
-                    ret.size.width = if little_endian {
-                        buffer.read_u16_le(12) as i64
-                    } else {
-                        buffer.read_u16_be(12) as i64
-                    };
-                } else if t == 4 {
-                    ret.size.width = if little_endian {
-                        buffer.read_u32_le(12) as i64
-                    } else {
-                        buffer.read_u32_be(12) as i64
-                    };
-                } else if t == 16 {
-                    let w = if little_endian {
-                        buffer.read_u64_le(12)
-                    } else {
-                        buffer.read_u64_be(12)
-                    };
-                    if w > (i64::MAX as u64) {
-                        // TODO: Size > INT64_MAX is not supported
-                        return Err(ImageInfoError::UnrecognizedFormat);
-                    }
-                    ret.size.width = w as i64;
-                }
-            } else if tag == 257 {
-                if t == 3 {
-                    ret.size.height = if little_endian {
-                        buffer.read_u16_le(12) as i64
-                    } else {
-                        buffer.read_u16_be(12) as i64
-                    };
-                } else if t == 4 {
-                    ret.size.height = if little_endian {
-                        buffer.read_u32_le(12) as i64
-                    } else {
-                        buffer.read_u32_be(12) as i64
-                    };
-                } else if t == 16 {
-                    let h = if little_endian {
-                        buffer.read_u64_le(12)
-                    } else {
-                        buffer.read_u64_be(12)
-                    };
-                    if h > (i64::MAX as u64) {
-                        // TODO: Size > INT64_MAX is not supported
-                        return Err(ImageInfoError::UnrecognizedFormat);
-                    }
-                    ret.size.height = h as i64;
-                }
+                size.height = value as i64;
             }
-
-            i += 1;
-            offset += 20;
         }
-
-        if ret.size.width != -1 && ret.size.height != -1 {
-            Ok(ret)
-        } else {
-            Err(ImageInfoError::UnrecognizedFormat)
+        if size.width != -1 && size.height != -1 {
+            break;
         }
-    } else {
+    }
+
+    if size.width == -1 || size.height == -1 {
         return Err(ImageInfoError::UnrecognizedFormat);
     }
+
+    // NewSubfileType, or the obsolete SubfileType mapped onto its bits
+    let mut subfile_type = 0;
+    if let Some(entry) = ifd.find(254) {
+        subfile_type = reader.read_uint(ri, entry, 0)?.unwrap_or(0) as u32;
+    } else if let Some(entry) = ifd.find(255) {
+        subfile_type = match reader.read_uint(ri, entry, 0)? {
+            Some(2) => 1,
+            Some(3) => 2,
+            _ => 0,
+        };
+    }
+
+    Ok(TiffPage { size, subfile_type })
 }
@@ -1,3 +1,4 @@
+use crate::details::{WebpChunk, WebpCompression, WebpDetails};
 use crate::{ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface};
 use std::cmp::min;
 use std::io::{BufRead, Seek};
@@ -11,21 +12,17 @@ where
         return Err(ImageInfoError::UnrecognizedFormat);
     }
     let buffer = ri.read(0, min(length, 30))?;
-    if !buffer.cmp_any_of(0, 4, vec![b"RIFF", b"WEBP"]) {
+    if !buffer.cmp(0, 4, b"RIFF") || !buffer.cmp(8, 4, b"WEBP") {
         return Err(ImageInfoError::UnrecognizedFormat);
     }
 
-    let mut ret = ImageInfo {
-        format: ImageFormat::WEBP,
-        ext: "webp",
-        full_ext: "webp",
-        mimetype: "image/webp",
-        size: ImageSize {
+    let mut ret = ImageInfo::from_format(
+        ImageFormat::WEBP,
+        ImageSize {
             width: 0,
             height: 0,
         },
-        entry_sizes: vec![],
-    };
+    );
 
     if buffer.cmp(12, 4, b"VP8 ") && buffer.len() >= 30 {
         ret.size.width = (buffer.read_u16_le(26) & 0x3FFF) as i64;
@@ -49,3 +46,122 @@ where
 
     Err(ImageInfoError::UnrecognizedFormat)
 }
+
+// Bitstream and alpha of a VP8/VP8L/ALPH chunk
+fn read_image_chunk<R>(
+    ri: &mut ReadInterface<R>,
+    chunk_type: &str,
+    offset: usize,
+    chunk_length: usize,
+    details: &mut WebpDetails,
+) -> ImageInfoResult<()>
+where
+    R: BufRead + Seek,
+{
+    match chunk_type {
+        "VP8 " if details.compression.is_none() => {
+            details.compression = Some(WebpCompression::Lossy);
+        }
+        "VP8L" if details.compression.is_none() => {
+            details.compression = Some(WebpCompression::Lossless);
+            // signature byte, then 14-bit width and height and the alpha_is_used bit
+            if chunk_length >= 5 {
+                let buffer = ri.read(offset + 8, 5)?;
+                if buffer.read_u32_le(1) & 0x10000000 != 0 {
+                    details.has_alpha = true;
+                }
+            }
+        }
+        "ALPH" => details.has_alpha = true,
+        _ => {}
+    }
+    Ok(())
+}
+
+// Walks the RIFF chunk list, and the chunks of the first ANMF frame
+pub fn read_webp_details<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+) -> ImageInfoResult<WebpDetails>
+where
+    R: BufRead + Seek,
+{
+    if length < 12 {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    let buffer = ri.read(0, 12)?;
+    if !buffer.cmp(0, 4, b"RIFF") || !buffer.cmp(8, 4, b"WEBP") {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    let riff_size = buffer.read_u32_le(4);
+    if riff_size < 4 {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+
+    let mut details = WebpDetails {
+        riff_size,
+        truncated: (riff_size as u64) + 8 > length as u64,
+        compression: None,
+        vp8x_flags: None,
+        has_alpha: false,
+        has_icc: false,
+        has_exif: false,
+        has_xmp: false,
+        is_animated: false,
+        chunks: vec![],
+    };
+
+    let end = min(riff_size as usize + 8, length);
+    let mut offset = 12usize;
+    while offset + 8 <= end {
+        let buffer = ri.read(offset, 8)?;
+        let chunk_type = buffer.read_str(0, 4);
+        let chunk_length = buffer.read_u32_le(4) as usize;
+        if offset + 8 + chunk_length > end {
+            details.truncated = true;
+            break;
+        }
+        details.chunks.push(WebpChunk {
+            chunk_type: chunk_type.clone(),
+            offset: offset as u64,
+            length: chunk_length as u32,
+        });
+
+        match chunk_type.as_str() {
+            "VP8X" if chunk_length >= 10 && offset == 12 => {
+                details.vp8x_flags = Some(ri.read(offset + 8, 1)?.read_u8(0));
+            }
+            "ICCP" => details.has_icc = true,
+            "EXIF" => details.has_exif = true,
+            "XMP " => details.has_xmp = true,
+            "ANIM" => details.is_animated = true,
+            "ANMF" => {
+                details.is_animated = true;
+                // 16-byte frame header, then the frame data chunks
+                let frame_end = offset + 8 + chunk_length;
+                let mut frame_offset = offset + 8 + 16;
+                while details.compression.is_none() && frame_offset + 8 <= frame_end {
+                    let buffer = ri.read(frame_offset, 8)?;
+                    let frame_chunk_type = buffer.read_str(0, 4);
+                    let frame_chunk_length = buffer.read_u32_le(4) as usize;
+                    if frame_offset + 8 + frame_chunk_length > frame_end {
+                        break;
+                    }
+                    read_image_chunk(
+                        ri,
+                        &frame_chunk_type,
+                        frame_offset,
+                        frame_chunk_length,
+                        &mut details,
+                    )?;
+                    frame_offset += 8 + frame_chunk_length + (frame_chunk_length & 1);
+                }
+            }
+            _ => read_image_chunk(ri, &chunk_type, offset, chunk_length, &mut details)?,
+        }
+        // chunk data is padded to an even size
+        offset += 8 + chunk_length + (chunk_length & 1);
+    }
+
+    Ok(details)
+}
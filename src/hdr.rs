@@ -0,0 +1,398 @@
+use crate::details::HeifAuxiliaryKind;
+use crate::formats::{parse_radiance_resolution, read_heif_details, read_radiance_header, try_jpg};
+use crate::heif::{read_heif_meta, HeifProperty};
+use crate::ifd::IfdReader;
+use crate::preview::probe_embedded;
+use crate::{detect, ImageFormat, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Seek, SeekFrom};
+use std::path::Path;
+use std::str::FromStr;
+
+const XMP_SIGNATURE: &[u8; 29] = b"http://ns.adobe.com/xap/1.0/\x00";
+
+// MP Index IFD tags of the multi-picture format (CIPA DC-007)
+const TAG_MP_ENTRY: u16 = 0xB002;
+
+// Namespaces marking the XMP of a gain map image: Ultra HDR and Apple
+const GAIN_MAP_XMP_MARKERS: [&str; 2] = ["http://ns.adobe.com/hdr-gain-map/1.0/", "HDRGainMap"];
+
+// Coding-independent code points (ITU-T H.273) of nclx colr and PNG cICP
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Cicp {
+    pub color_primaries: u16,
+    pub transfer_characteristics: u16,
+    pub matrix_coefficients: u16,
+    pub full_range: bool,
+}
+
+impl Cicp {
+    // SMPTE ST 2084
+    pub fn is_pq(&self) -> bool {
+        self.transfer_characteristics == 16
+    }
+
+    // ARIB STD-B67
+    pub fn is_hlg(&self) -> bool {
+        self.transfer_characteristics == 18
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct GainMap {
+    // absolute offset and length of the gain map image data
+    pub offset: u64,
+    pub length: u64,
+    pub size: Option<ImageSize>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct RadianceHeader {
+    pub format: Option<String>,
+    // product of all EXPOSURE lines
+    pub exposure: Option<f64>,
+    pub gamma: Option<f64>,
+    // CIE (x, y) of red, green, blue and white
+    pub primaries: Option<[f64; 8]>,
+    // product of all PIXASPECT lines
+    pub pixel_aspect: Option<f64>,
+    pub software: Option<String>,
+    // EXIF orientation of the resolution string, 1 for "-Y h +X w"
+    pub orientation: Option<u16>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct HdrMetadata {
+    pub cicp: Option<Cicp>,
+    pub gain_map: Option<GainMap>,
+    // hdrgm:Version of an Ultra HDR jpeg
+    pub ultra_hdr_version: Option<String>,
+    pub radiance: Option<RadianceHeader>,
+}
+
+impl HdrMetadata {
+    pub fn is_hdr(&self) -> bool {
+        matches!(&self.cicp, Some(cicp) if cicp.is_pq() || cicp.is_hlg())
+            || self.gain_map.is_some()
+            || self.ultra_hdr_version.is_some()
+            || self.radiance.is_some()
+    }
+
+    pub fn from_reader<R>(reader: &mut R) -> ImageInfoResult<HdrMetadata>
+    where
+        R: BufRead + Seek,
+    {
+        let length = reader.seek(SeekFrom::End(0))? as usize;
+        let mut ri = ReadInterface::from_reader(reader, length);
+        let info = detect(&mut ri, length)?;
+
+        let mut hdr = HdrMetadata::default();
+        let ret = match info.format {
+            ImageFormat::AVIF | ImageFormat::HEIC => read_heif_hdr(&mut ri, length, &mut hdr),
+            ImageFormat::PNG => read_png_hdr(&mut ri, length, &mut hdr),
+            ImageFormat::JPEG => read_jpg_hdr(&mut ri, length, &mut hdr),
+            ImageFormat::HDR => read_radiance_hdr(&mut ri, length, &mut hdr),
+            _ => Ok(()),
+        };
+        match ret {
+            Ok(()) | Err(ImageInfoError::UnrecognizedFormat) => Ok(hdr),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn from_file(file: &File) -> ImageInfoResult<HdrMetadata> {
+        let mut reader = BufReader::new(file);
+        Self::from_reader(&mut reader)
+    }
+
+    pub fn from_file_path(filepath: impl AsRef<Path>) -> ImageInfoResult<HdrMetadata> {
+        let file = File::open(filepath)?;
+        Self::from_file(&file)
+    }
+
+    pub fn from_raw_data(data: &[u8]) -> ImageInfoResult<HdrMetadata> {
+        let mut reader = BufReader::new(Cursor::new(data));
+        Self::from_reader(&mut reader)
+    }
+}
+
+// Value of an XMP property, written either as attribute or as element
+pub fn xmp_value(xmp: &str, name: &str) -> Option<String> {
+    let attribute = format!("{}=\"", name);
+    if let Some(start) = xmp.find(&attribute).map(|pos| pos + attribute.len()) {
+        let end = xmp[start..].find('"')?;
+        return Some(xmp[start..start + end].to_string());
+    }
+    let element = format!("<{}>", name);
+    let start = xmp.find(&element)? + element.len();
+    let end = xmp[start..].find('<')?;
+    Some(xmp[start..start + end].trim().to_string())
+}
+
+// nclx colr of the primary item, gain map from auxl or tmap items
+fn read_heif_hdr<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    hdr: &mut HdrMetadata,
+) -> ImageInfoResult<()>
+where
+    R: BufRead + Seek,
+{
+    let meta = read_heif_meta(ri, length)?;
+    hdr.cicp = meta
+        .item_properties(meta.primary_item)
+        .into_iter()
+        .find_map(|property| match property {
+            HeifProperty::Nclx(primaries, transfer, matrix, full_range) => Some(Cicp {
+                color_primaries: *primaries,
+                transfer_characteristics: *transfer,
+                matrix_coefficients: *matrix,
+                full_range: *full_range,
+            }),
+            _ => None,
+        });
+
+    let details = read_heif_details(ri, &meta)?;
+    let gain_map = details
+        .auxiliary_items
+        .into_iter()
+        .find(|aux| aux.kind == HeifAuxiliaryKind::GainMap);
+    if let Some(gain_map) = gain_map {
+        let item = meta.items.iter().find(|item| item.id == gain_map.item.id);
+        // only items stored in a single extent can be read in one range
+        if let Some([(offset, size)]) = item.map(|item| item.extents.as_slice()) {
+            hdr.gain_map = Some(GainMap {
+                offset: *offset,
+                length: *size,
+                size: gain_map.item.size,
+            });
+        }
+    }
+    Ok(())
+}
+
+// cICP chunk, it has to precede PLTE and IDAT
+fn read_png_hdr<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    hdr: &mut HdrMetadata,
+) -> ImageInfoResult<()>
+where
+    R: BufRead + Seek,
+{
+    let mut offset = 8usize;
+    while offset + 12 <= length {
+        let buffer = ri.read(offset, 8)?;
+        let chunk_size = buffer.read_u32_be(0) as usize;
+        if offset + 12 + chunk_size > length {
+            break;
+        }
+        if buffer.cmp(4, 4, b"cICP") && chunk_size == 4 {
+            let buffer = ri.read(offset + 8, 4)?;
+            hdr.cicp = Some(Cicp {
+                color_primaries: buffer.read_u8(0) as u16,
+                transfer_characteristics: buffer.read_u8(1) as u16,
+                matrix_coefficients: buffer.read_u8(2) as u16,
+                full_range: buffer.read_u8(3) != 0,
+            });
+            break;
+        }
+        if buffer.cmp_any_of(4, 4, vec![b"PLTE", b"IDAT", b"IEND"]) {
+            break;
+        }
+        offset += 12 + chunk_size;
+    }
+    Ok(())
+}
+
+// Start and end offsets
+type Range = (usize, usize);
+
+// XMP packet and MPF block (APP2 "MPF\0") of a jpeg, as (start, end) ranges
+fn find_jpg_segments<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+) -> ImageInfoResult<(Option<Range>, Option<Range>)>
+where
+    R: BufRead + Seek,
+{
+    let mut xmp = None;
+    let mut mpf = None;
+    let mut offset = 2usize;
+    while offset + 4 <= length {
+        let buffer = ri.read(offset, 4)?;
+        if !buffer.cmp(0, 1, b"\xFF") {
+            // skip garbage bytes
+            offset += 1;
+            continue;
+        }
+        // Start of scan, no more metadata
+        if buffer.cmp_any_of(0, 2, vec![b"\xFF\xDA", b"\xFF\xD9"]) {
+            break;
+        }
+        let section_size = buffer.read_u16_be(2) as usize;
+        let end = offset + section_size + 2;
+        if end > length {
+            break;
+        }
+        if buffer.cmp(0, 2, b"\xFF\xE1") && xmp.is_none() && section_size >= 2 + 29 {
+            let buffer = ri.read(offset + 4, 29)?;
+            if buffer.cmp(0, 29, XMP_SIGNATURE) {
+                xmp = Some((offset + 4 + 29, end));
+            }
+        } else if buffer.cmp(0, 2, b"\xFF\xE2") && mpf.is_none() && section_size >= 2 + 4 {
+            let buffer = ri.read(offset + 4, 4)?;
+            if buffer.cmp(0, 4, b"MPF\x00") {
+                mpf = Some((offset + 8, end));
+            }
+        }
+        offset += section_size + 2;
+    }
+    Ok((xmp, mpf))
+}
+
+fn read_jpg_xmp<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<Option<String>>
+where
+    R: BufRead + Seek,
+{
+    match find_jpg_segments(ri, length)?.0 {
+        Some((start, end)) => Ok(Some(ri.read(start, end - start)?.read_str_all())),
+        None => Ok(None),
+    }
+}
+
+// Offsets and sizes of the images listed in the MP Index IFD, absolute
+//
+// https://www.cipa.jp/std/documents/e/DC-X007-KEY_E.pdf
+fn read_mp_entries<R>(
+    ri: &mut ReadInterface<R>,
+    base: usize,
+    end: usize,
+) -> ImageInfoResult<Vec<(usize, usize)>>
+where
+    R: BufRead + Seek,
+{
+    let reader = IfdReader::new(ri, base, end)?;
+    let ifd = reader.read_ifd(ri, reader.first_ifd)?;
+    let entry = match ifd.find(TAG_MP_ENTRY) {
+        Some(entry) => entry,
+        None => return Ok(vec![]),
+    };
+    let count = entry.count as usize / 16;
+    let data_offset = entry.data_offset as usize;
+    if count == 0 || data_offset.saturating_add(count * 16) > end {
+        return Ok(vec![]);
+    }
+    let buffer = ri.read(data_offset, count * 16)?;
+    let mut entries = vec![];
+    for i in 0..count {
+        let size = buffer.read_u32(i * 16 + 4, reader.little_endian) as usize;
+        let offset = buffer.read_u32(i * 16 + 8, reader.little_endian) as usize;
+        // the first image starts at SOI of the file, others are relative to the MPF header
+        let offset = if i == 0 { 0 } else { base + offset };
+        entries.push((offset, size));
+    }
+    Ok(entries)
+}
+
+// Ultra HDR and Apple gain maps are secondary images of the MPF block
+fn read_jpg_hdr<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    hdr: &mut HdrMetadata,
+) -> ImageInfoResult<()>
+where
+    R: BufRead + Seek,
+{
+    let (xmp, mpf) = find_jpg_segments(ri, length)?;
+    if let Some((start, end)) = xmp {
+        let xmp = ri.read(start, end - start)?.read_str_all();
+        hdr.ultra_hdr_version = xmp_value(&xmp, "hdrgm:Version");
+    }
+
+    let (base, end) = match mpf {
+        Some(mpf) => mpf,
+        None => return Ok(()),
+    };
+    for (offset, size) in read_mp_entries(ri, base, end)?.into_iter().skip(1) {
+        if size == 0 || offset.saturating_add(size) > length {
+            continue;
+        }
+        let xmp = {
+            let mut sub = ri.sub_interface(offset, size);
+            if try_jpg(&mut sub, size).is_err() {
+                continue;
+            }
+            read_jpg_xmp(&mut sub, size)?
+        };
+        let is_gain_map = match xmp {
+            Some(xmp) => GAIN_MAP_XMP_MARKERS
+                .iter()
+                .any(|marker| xmp.contains(marker)),
+            None => false,
+        };
+        if is_gain_map || hdr.ultra_hdr_version.is_some() {
+            hdr.gain_map = Some(GainMap {
+                offset: offset as u64,
+                length: size as u64,
+                size: probe_embedded(ri, offset, size)?.map(|info| info.size),
+            });
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn read_radiance_hdr<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    hdr: &mut HdrMetadata,
+) -> ImageInfoResult<()>
+where
+    R: BufRead + Seek,
+{
+    let (lines, resolution) = read_radiance_header(ri, length)?;
+    let mut radiance = RadianceHeader {
+        orientation: parse_radiance_resolution(&resolution).map(|(_, orientation)| orientation),
+        ..Default::default()
+    };
+    for line in lines.iter() {
+        let (name, value) = match line.trim_start().split_once('=') {
+            Some((name, value)) => (name, value.trim()),
+            None => continue,
+        };
+        match name {
+            "FORMAT" => radiance.format = Some(value.to_string()),
+            "SOFTWARE" => radiance.software = Some(value.to_string()),
+            "EXPOSURE" => {
+                if let Ok(exposure) = f64::from_str(value) {
+                    radiance.exposure = Some(radiance.exposure.unwrap_or(1.0) * exposure);
+                }
+            }
+            "PIXASPECT" => {
+                if let Ok(pixel_aspect) = f64::from_str(value) {
+                    radiance.pixel_aspect =
+                        Some(radiance.pixel_aspect.unwrap_or(1.0) * pixel_aspect);
+                }
+            }
+            "GAMMA" => radiance.gamma = f64::from_str(value).ok(),
+            "PRIMARIES" => {
+                let values = value.split_whitespace().map(f64::from_str);
+                let values: Result<Vec<f64>, _> = values.collect();
+                if let Some(Ok(primaries)) = values.ok().map(<[f64; 8]>::try_from) {
+                    radiance.primaries = Some(primaries);
+                }
+            }
+            _ => {}
+        }
+    }
+    hdr.radiance = Some(radiance);
+    Ok(())
+}
@@ -0,0 +1,592 @@
+use crate::{ImageInfoError, ImageInfoResult, ImageSize, RawBuffer, ReadInterface};
+use std::io::{BufRead, Seek};
+
+pub struct HeifItem {
+    pub id: u32,
+    pub item_type: String,
+    // absolute offset and length of each extent of the item data
+    pub extents: Vec<(u64, u64)>,
+}
+
+pub struct HeifReference {
+    pub reference_type: String,
+    pub from: u32,
+    pub to: Vec<u32>,
+}
+
+pub enum HeifProperty {
+    Ispe(u32, u32),
+    Irot(u8),
+    // clean aperture width and height as numerator/denominator pairs
+    Clap((u32, u32), (u32, u32)),
+    // auxiliary type URN
+    AuxC(String),
+    // colour primaries, transfer characteristics, matrix coefficients and
+    // full range flag of an nclx colr
+    Nclx(u16, u16, u16, bool),
+    Other,
+}
+
+// Output of a derived image item, the grid layout is only set for grid items
+pub struct HeifDerivedImage {
+    pub grid: Option<(u32, u32)>,
+    pub output_size: ImageSize,
+}
+
+// Upper bound of bytes read from an item for the derived image header
+const MAX_DERIVED_HEADER_SIZE: u64 = 18;
+
+pub struct HeifMeta {
+    pub primary_item: u32,
+    pub items: Vec<HeifItem>,
+    pub references: Vec<HeifReference>,
+    // children of ipco, ipma indices are 1-based
+    pub properties: Vec<HeifProperty>,
+    // item id and the indices of its properties
+    pub associations: Vec<(u32, Vec<u16>)>,
+}
+
+impl HeifMeta {
+    pub fn find_item(&self, item_type: &str) -> Option<&HeifItem> {
+        self.items.iter().find(|item| item.item_type == item_type)
+    }
+
+    pub fn item_properties(&self, id: u32) -> Vec<&HeifProperty> {
+        self.associations
+            .iter()
+            .filter(|(item_id, _)| *item_id == id)
+            .flat_map(|(_, indices)| indices.iter())
+            .filter_map(|&index| {
+                if index == 0 {
+                    None
+                } else {
+                    self.properties.get(index as usize - 1)
+                }
+            })
+            .collect()
+    }
+
+    // grid and iovl items store their output size in the item data
+    //
+    // https://github.com/MPEGGroup/FileFormat/blob/master/HEIF/ISO_IEC_23008-12.pdf
+    pub fn read_derived_image<R>(
+        &self,
+        ri: &mut ReadInterface<R>,
+        id: u32,
+    ) -> ImageInfoResult<Option<HeifDerivedImage>>
+    where
+        R: BufRead + Seek,
+    {
+        let item = match self.items.iter().find(|item| item.id == id) {
+            Some(item) => item,
+            None => return Ok(None),
+        };
+        let is_grid = item.item_type == "grid";
+        if !is_grid && item.item_type != "iovl" {
+            return Ok(None);
+        }
+        // the header is a handful of bytes, expect it in the first extent
+        let (offset, size) = match item.extents.first() {
+            Some(&(offset, size)) => (offset, size.min(MAX_DERIVED_HEADER_SIZE)),
+            None => return Ok(None),
+        };
+        if size < 4 || offset.saturating_add(size) > ri.length as u64 {
+            return Ok(None);
+        }
+        let buffer = ri.read(offset as usize, size as usize)?;
+        let version = buffer.read_u8(0);
+        let field_size = if buffer.read_u8(1) & 0x01 != 0 { 4 } else { 2 };
+        // grid: rows and columns, iovl: four 16-bit canvas fill values
+        let (grid, size_offset) = if is_grid {
+            let rows = buffer.read_u8(2) as u32 + 1;
+            let columns = buffer.read_u8(3) as u32 + 1;
+            (Some((rows, columns)), 4)
+        } else {
+            (None, 10)
+        };
+        if version != 0 || size_offset + 2 * field_size > buffer.len() {
+            return Ok(None);
+        }
+        let width = read_sized_uint(&buffer, size_offset, field_size)?;
+        let height = read_sized_uint(&buffer, size_offset + field_size, field_size)?;
+        Ok(Some(HeifDerivedImage {
+            grid,
+            output_size: ImageSize {
+                width: width as i64,
+                height: height as i64,
+            },
+        }))
+    }
+
+    // Displayed size of the item: output size of derived images or ispe,
+    // with clap and irot applied in the order they are associated
+    pub fn item_size<R>(
+        &self,
+        ri: &mut ReadInterface<R>,
+        id: u32,
+    ) -> ImageInfoResult<Option<ImageSize>>
+    where
+        R: BufRead + Seek,
+    {
+        let properties = self.item_properties(id);
+        // falls back to ispe if the derived image header can't be read
+        let derived = match self.read_derived_image(ri, id) {
+            Err(ImageInfoError::UnrecognizedFormat) => None,
+            derived => derived?,
+        };
+        let mut size = match derived {
+            Some(derived) => derived.output_size,
+            None => match properties.iter().find_map(|property| match property {
+                HeifProperty::Ispe(width, height) => Some(ImageSize {
+                    width: *width as i64,
+                    height: *height as i64,
+                }),
+                _ => None,
+            }) {
+                Some(size) => size,
+                None => return Ok(None),
+            },
+        };
+        for property in properties {
+            match property {
+                HeifProperty::Clap(width, height) => {
+                    size.width = clean_aperture(*width, size.width);
+                    size.height = clean_aperture(*height, size.height);
+                }
+                HeifProperty::Irot(irot) if irot & 0x01 != 0 => {
+                    std::mem::swap(&mut size.width, &mut size.height);
+                }
+                _ => {}
+            }
+        }
+        Ok(Some(size))
+    }
+
+    pub fn item_aux_type(&self, id: u32) -> Option<&str> {
+        self.item_properties(id)
+            .into_iter()
+            .find_map(|property| match property {
+                HeifProperty::AuxC(aux_type) => Some(aux_type.as_str()),
+                _ => None,
+            })
+    }
+
+    // Items the given item refers to with the given reference type
+    pub fn referenced_items(&self, reference_type: &str, from: u32) -> Vec<u32> {
+        self.references
+            .iter()
+            .filter(|reference| {
+                reference.reference_type == reference_type && reference.from == from
+            })
+            .flat_map(|reference| reference.to.iter().cloned())
+            .collect()
+    }
+
+    // Items referring to the given item with the given reference type
+    pub fn referring_items(&self, reference_type: &str, to: u32) -> Vec<u32> {
+        self.references
+            .iter()
+            .filter(|reference| {
+                reference.reference_type == reference_type && reference.to.contains(&to)
+            })
+            .map(|reference| reference.from)
+            .collect()
+    }
+}
+
+// Offset, header size and total size of the first top level box of the given type
+pub fn find_box<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    box_type: &[u8],
+) -> ImageInfoResult<Option<(usize, usize, usize)>>
+where
+    R: BufRead + Seek,
+{
+    let mut offset = 0usize;
+    while offset + 8 <= length {
+        let buffer = ri.read(offset, 8)?;
+        let mut header_size = 8usize;
+        let mut box_size = buffer.read_u32_be(0) as u64;
+        if box_size == 1 {
+            if offset + 16 > length {
+                break;
+            }
+            box_size = ri.read(offset + 8, 8)?.read_u64_be(0);
+            header_size = 16;
+        } else if box_size == 0 {
+            box_size = (length - offset) as u64;
+        }
+        if box_size < header_size as u64 || box_size > (length - offset) as u64 {
+            break;
+        }
+        if buffer.cmp(4, 4, box_type) {
+            return Ok(Some((offset, header_size, box_size as usize)));
+        }
+        offset += box_size as usize;
+    }
+    Ok(None)
+}
+
+// Rounded clean aperture dimension, never larger than the uncropped one
+fn clean_aperture((numerator, denominator): (u32, u32), size: i64) -> i64 {
+    if denominator == 0 || numerator == 0 {
+        return size;
+    }
+    let value = (numerator as i64 + denominator as i64 / 2) / denominator as i64;
+    value.min(size)
+}
+
+fn read_sized_uint(buffer: &RawBuffer, offset: usize, size: usize) -> ImageInfoResult<u64> {
+    if offset + size > buffer.len() {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    match size {
+        0 => Ok(0),
+        2 => Ok(buffer.read_u16_be(offset) as u64),
+        4 => Ok(buffer.read_u32_be(offset) as u64),
+        8 => Ok(buffer.read_u64_be(offset)),
+        _ => Err(ImageInfoError::UnrecognizedFormat),
+    }
+}
+
+fn parse_pitm(buffer: &RawBuffer, start: usize, end: usize) -> ImageInfoResult<u32> {
+    let version = buffer.read_u8(start);
+    let id_size = if version == 0 { 2 } else { 4 };
+    if start + 4 + id_size > end {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    Ok(read_sized_uint(buffer, start + 4, id_size)? as u32)
+}
+
+fn parse_iinf(buffer: &RawBuffer, start: usize, end: usize) -> ImageInfoResult<Vec<HeifItem>> {
+    let version = buffer.read_u8(start);
+    let mut offset = start + if version == 0 { 6 } else { 8 };
+    let mut items = vec![];
+    while offset + 12 <= end {
+        let box_size = buffer.read_u32_be(offset) as usize;
+        if box_size < 12 || offset + box_size > end {
+            return Err(ImageInfoError::UnrecognizedFormat);
+        }
+        if buffer.cmp(offset + 4, 4, b"infe") {
+            // Only version 2 and 3 carry the item type
+            let infe_version = buffer.read_u8(offset + 8);
+            if infe_version >= 2 {
+                let id_size = if infe_version == 2 { 2 } else { 4 };
+                if 12 + id_size + 6 > box_size {
+                    return Err(ImageInfoError::UnrecognizedFormat);
+                }
+                items.push(HeifItem {
+                    id: read_sized_uint(buffer, offset + 12, id_size)? as u32,
+                    item_type: buffer.read_str(offset + 12 + id_size + 2, 4),
+                    extents: vec![],
+                });
+            }
+        }
+        offset += box_size;
+    }
+    Ok(items)
+}
+
+// Item id, construction method and extents relative to the base of the construction method
+type ItemLocation = (u32, u16, Vec<(u64, u64)>);
+
+fn parse_iloc(buffer: &RawBuffer, start: usize, end: usize) -> ImageInfoResult<Vec<ItemLocation>> {
+    if start + 8 > end {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    let version = buffer.read_u8(start);
+    if version > 2 {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    let offset_size = (buffer.read_u8(start + 4) >> 4) as usize;
+    let length_size = (buffer.read_u8(start + 4) & 0x0F) as usize;
+    let base_offset_size = (buffer.read_u8(start + 5) >> 4) as usize;
+    let index_size = if version == 0 {
+        0
+    } else {
+        (buffer.read_u8(start + 5) & 0x0F) as usize
+    };
+    let id_size = if version < 2 { 2 } else { 4 };
+
+    let mut offset = start + 6;
+    let item_count = read_sized_uint(buffer, offset, id_size)?;
+    offset += id_size;
+
+    let mut locations = vec![];
+    for _ in 0..item_count {
+        let id = read_sized_uint(buffer, offset, id_size)? as u32;
+        offset += id_size;
+        let mut construction_method = 0u16;
+        if version > 0 {
+            construction_method = read_sized_uint(buffer, offset, 2)? as u16 & 0x0F;
+            offset += 2;
+        }
+        // data_reference_index
+        offset += 2;
+        let base_offset = read_sized_uint(buffer, offset, base_offset_size)?;
+        offset += base_offset_size;
+        let extent_count = read_sized_uint(buffer, offset, 2)? as usize;
+        offset += 2;
+        // offset and length may both be 0 bytes: the item is the whole file
+        let extent_size = index_size + offset_size + length_size;
+        if offset + extent_count * extent_size > end {
+            return Err(ImageInfoError::UnrecognizedFormat);
+        }
+        let mut extents = vec![];
+        for _ in 0..extent_count {
+            offset += index_size;
+            let extent_offset = read_sized_uint(buffer, offset, offset_size)?;
+            offset += offset_size;
+            let extent_length = read_sized_uint(buffer, offset, length_size)?;
+            offset += length_size;
+            extents.push((base_offset.saturating_add(extent_offset), extent_length));
+        }
+        locations.push((id, construction_method, extents));
+    }
+    Ok(locations)
+}
+
+fn parse_iref(buffer: &RawBuffer, start: usize, end: usize) -> ImageInfoResult<Vec<HeifReference>> {
+    let version = buffer.read_u8(start);
+    let id_size = if version == 0 { 2 } else { 4 };
+    let mut offset = start + 4;
+    let mut references = vec![];
+    while offset + 8 <= end {
+        let box_size = buffer.read_u32_be(offset) as usize;
+        if box_size < 8 || offset + box_size > end {
+            return Err(ImageInfoError::UnrecognizedFormat);
+        }
+        let mut t = offset + 8;
+        let from = read_sized_uint(buffer, t, id_size)? as u32;
+        t += id_size;
+        let reference_count = read_sized_uint(buffer, t, 2)? as usize;
+        t += 2;
+        if t + reference_count * id_size > offset + box_size {
+            return Err(ImageInfoError::UnrecognizedFormat);
+        }
+        let mut to = vec![];
+        for _ in 0..reference_count {
+            to.push(read_sized_uint(buffer, t, id_size)? as u32);
+            t += id_size;
+        }
+        references.push(HeifReference {
+            reference_type: buffer.read_str(offset + 4, 4),
+            from,
+            to,
+        });
+        offset += box_size;
+    }
+    Ok(references)
+}
+
+fn parse_ipco(buffer: &RawBuffer, start: usize, end: usize) -> ImageInfoResult<Vec<HeifProperty>> {
+    let mut offset = start;
+    let mut properties = vec![];
+    while offset + 8 <= end {
+        let box_size = buffer.read_u32_be(offset) as usize;
+        if box_size < 8 || offset + box_size > end {
+            return Err(ImageInfoError::UnrecognizedFormat);
+        }
+        let property = if buffer.cmp(offset + 4, 4, b"ispe") {
+            if box_size < 20 {
+                return Err(ImageInfoError::UnrecognizedFormat);
+            }
+            HeifProperty::Ispe(
+                buffer.read_u32_be(offset + 12),
+                buffer.read_u32_be(offset + 16),
+            )
+        } else if buffer.cmp(offset + 4, 4, b"irot") {
+            if box_size < 9 {
+                return Err(ImageInfoError::UnrecognizedFormat);
+            }
+            HeifProperty::Irot(buffer.read_u8(offset + 8) & 0x03)
+        } else if buffer.cmp(offset + 4, 4, b"clap") {
+            if box_size < 40 {
+                return Err(ImageInfoError::UnrecognizedFormat);
+            }
+            HeifProperty::Clap(
+                (
+                    buffer.read_u32_be(offset + 8),
+                    buffer.read_u32_be(offset + 12),
+                ),
+                (
+                    buffer.read_u32_be(offset + 16),
+                    buffer.read_u32_be(offset + 20),
+                ),
+            )
+        } else if buffer.cmp(offset + 4, 4, b"colr")
+            && box_size >= 19
+            && buffer.cmp(offset + 8, 4, b"nclx")
+        {
+            HeifProperty::Nclx(
+                buffer.read_u16_be(offset + 12),
+                buffer.read_u16_be(offset + 14),
+                buffer.read_u16_be(offset + 16),
+                buffer.read_u8(offset + 18) & 0x80 != 0,
+            )
+        } else if buffer.cmp(offset + 4, 4, b"auxC") {
+            if box_size < 12 {
+                return Err(ImageInfoError::UnrecognizedFormat);
+            }
+            let urn_length = (offset + 12..offset + box_size)
+                .position(|i| buffer.read_u8(i) == 0)
+                .unwrap_or(box_size - 12);
+            HeifProperty::AuxC(buffer.read_str(offset + 12, urn_length))
+        } else {
+            HeifProperty::Other
+        };
+        properties.push(property);
+        offset += box_size;
+    }
+    Ok(properties)
+}
+
+fn parse_ipma(
+    buffer: &RawBuffer,
+    start: usize,
+    end: usize,
+) -> ImageInfoResult<Vec<(u32, Vec<u16>)>> {
+    if start + 8 > end {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    let version = buffer.read_u8(start);
+    let large_index = buffer.read_u8(start + 3) & 0x01 != 0;
+    let id_size = if version < 1 { 2 } else { 4 };
+    let index_size = if large_index { 2 } else { 1 };
+    let entry_count = buffer.read_u32_be(start + 4);
+    let mut offset = start + 8;
+    let mut associations = vec![];
+    for _ in 0..entry_count {
+        if offset + id_size + 1 > end {
+            return Err(ImageInfoError::UnrecognizedFormat);
+        }
+        let id = read_sized_uint(buffer, offset, id_size)? as u32;
+        offset += id_size;
+        let count = buffer.read_u8(offset) as usize;
+        offset += 1;
+        if offset + count * index_size > end {
+            return Err(ImageInfoError::UnrecognizedFormat);
+        }
+        let mut indices = vec![];
+        for _ in 0..count {
+            // the highest bit is the essential flag
+            let index = if large_index {
+                buffer.read_u16_be(offset) & 0x7FFF
+            } else {
+                (buffer.read_u8(offset) & 0x7F) as u16
+            };
+            indices.push(index);
+            offset += index_size;
+        }
+        associations.push((id, indices));
+    }
+    Ok(associations)
+}
+
+fn parse_iprp(
+    buffer: &RawBuffer,
+    start: usize,
+    end: usize,
+    meta: &mut HeifMeta,
+) -> ImageInfoResult<()> {
+    let mut offset = start;
+    while offset + 8 <= end {
+        let box_size = buffer.read_u32_be(offset) as usize;
+        if box_size < 8 || offset + box_size > end {
+            return Err(ImageInfoError::UnrecognizedFormat);
+        }
+        if buffer.cmp(offset + 4, 4, b"ipco") {
+            meta.properties = parse_ipco(buffer, offset + 8, offset + box_size)?;
+        } else if buffer.cmp(offset + 4, 4, b"ipma") {
+            let associations = parse_ipma(buffer, offset + 8, offset + box_size)?;
+            meta.associations.extend(associations);
+        }
+        offset += box_size;
+    }
+    Ok(())
+}
+
+// https://nokiatech.github.io/heif/technical.html
+//
+// meta
+//   - pitm
+//   - iinf
+//       - infe
+//   - iloc
+//   - idat
+//   - iref
+//   - iprp
+//       - ipco
+//       - ipma
+pub fn read_heif_meta<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<HeifMeta>
+where
+    R: BufRead + Seek,
+{
+    let (meta_offset, header_size, meta_size) = match find_box(ri, length, b"meta")? {
+        Some(meta) => meta,
+        None => return Err(ImageInfoError::UnrecognizedFormat),
+    };
+    // meta is a full box, skip version and flags
+    if meta_size < header_size + 4 {
+        return Err(ImageInfoError::UnrecognizedFormat);
+    }
+    let end = meta_offset + meta_size;
+
+    let mut meta = HeifMeta {
+        primary_item: 1,
+        items: vec![],
+        references: vec![],
+        properties: vec![],
+        associations: vec![],
+    };
+    let mut locations = vec![];
+    let mut idat_offset = None;
+    let mut offset = meta_offset + header_size + 4;
+    while offset + 8 <= end {
+        let header = ri.read(offset, 8)?;
+        let box_size = header.read_u32_be(0) as usize;
+        if box_size < 8 || box_size > end - offset {
+            break;
+        }
+        // idat and anything else can be large, only read the boxes parsed here
+        if header.cmp(4, 4, b"idat") {
+            idat_offset = Some((offset + 8) as u64);
+        } else if header.cmp_any_of(4, 4, vec![b"pitm", b"iinf", b"iloc", b"iref", b"iprp"]) {
+            let buffer = ri.read(offset, box_size)?;
+            if buffer.cmp(4, 4, b"iprp") {
+                parse_iprp(&buffer, 8, box_size, &mut meta)?;
+            } else if box_size >= 12 {
+                // items, locations and references only matter for derived and
+                // auxiliary images, a malformed one doesn't hide the ispe size
+                if buffer.cmp(4, 4, b"pitm") {
+                    meta.primary_item = parse_pitm(&buffer, 8, box_size)?;
+                } else if buffer.cmp(4, 4, b"iinf") {
+                    meta.items = parse_iinf(&buffer, 8, box_size).unwrap_or_default();
+                } else if buffer.cmp(4, 4, b"iloc") {
+                    locations = parse_iloc(&buffer, 8, box_size).unwrap_or_default();
+                } else if buffer.cmp(4, 4, b"iref") {
+                    meta.references = parse_iref(&buffer, 8, box_size).unwrap_or_default();
+                }
+            }
+        }
+        offset += box_size;
+    }
+
+    for (id, construction_method, extents) in locations {
+        // 0: file offset, 1: idat offset, item offset is not supported
+        let base = match (construction_method, idat_offset) {
+            (0, _) => 0,
+            (1, Some(idat_offset)) => idat_offset,
+            _ => continue,
+        };
+        if let Some(item) = meta.items.iter_mut().find(|item| item.id == id) {
+            item.extents = extents
+                .iter()
+                .map(|(offset, length)| (base.saturating_add(*offset), *length))
+                .collect();
+        }
+    }
+
+    Ok(meta)
+}
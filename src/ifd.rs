@@ -0,0 +1,302 @@
+use crate::{ImageInfoError, ImageInfoResult, RawBuffer, ReadInterface};
+use std::cmp::min;
+use std::collections::HashSet;
+use std::io::{BufRead, Seek};
+
+// Upper bound of entries read from a single IFD, real files stay far below it
+const MAX_IFD_ENTRIES: u64 = 0xFFFF;
+
+// Upper bound of bytes read for a single ASCII value
+const MAX_ASCII_LENGTH: u64 = 4096;
+
+// Upper bound of IFDs followed through next IFD offsets
+const MAX_IFD_CHAIN_LENGTH: usize = 4096;
+
+pub struct IfdEntry {
+    pub tag: u16,
+    pub field_type: u16,
+    pub count: u64,
+    // absolute offset of the value, either inline in the entry or out of line
+    pub data_offset: u64,
+}
+
+pub struct Ifd {
+    pub entries: Vec<IfdEntry>,
+    // relative to the tiff header, 0 if this is the last IFD
+    pub next: u64,
+}
+
+impl Ifd {
+    pub fn find(&self, tag: u16) -> Option<&IfdEntry> {
+        self.entries.iter().find(|entry| entry.tag == tag)
+    }
+}
+
+// Reader of a TIFF structure (classic or BigTIFF), shared by tiff files and
+// EXIF blocks embedded in other containers.
+//
+// https://www.fileformat.info/format/tiff/corion.htm
+// https://www.awaresystems.be/imaging/tiff/bigtiff.html
+pub struct IfdReader {
+    // absolute offset of the tiff header, all IFD offsets are relative to it
+    pub base: usize,
+    // absolute end of the tiff data
+    pub end: usize,
+    pub little_endian: bool,
+    pub big_tiff: bool,
+    pub first_ifd: u64,
+}
+
+fn field_type_size(field_type: u16) -> u64 {
+    match field_type {
+        // BYTE, ASCII, SBYTE, UNDEFINED
+        1 | 2 | 6 | 7 => 1,
+        // SHORT, SSHORT
+        3 | 8 => 2,
+        // LONG, SLONG, FLOAT, IFD
+        4 | 9 | 11 | 13 => 4,
+        // RATIONAL, SRATIONAL, DOUBLE, LONG8, SLONG8, IFD8
+        5 | 10 | 12 | 16 | 17 | 18 => 8,
+        _ => 0,
+    }
+}
+
+impl IfdReader {
+    pub fn new<R>(ri: &mut ReadInterface<R>, base: usize, end: usize) -> ImageInfoResult<IfdReader>
+    where
+        R: BufRead + Seek,
+    {
+        if end < base || end - base < 8 {
+            return Err(ImageInfoError::UnrecognizedFormat);
+        }
+        let buffer = ri.read(base, 8)?;
+        if !buffer.cmp_any_of(0, 2, vec![b"\x49\x49", b"\x4D\x4D"]) {
+            return Err(ImageInfoError::UnrecognizedFormat);
+        }
+        let little_endian = buffer.data[0] == 0x49;
+        let version = buffer.read_u16(2, little_endian);
+
+        if version == 0x2A {
+            Ok(IfdReader {
+                base,
+                end,
+                little_endian,
+                big_tiff: false,
+                first_ifd: buffer.read_u32(4, little_endian) as u64,
+            })
+        } else if version == 0x2B {
+            if end - base < 16 {
+                return Err(ImageInfoError::UnrecognizedFormat);
+            }
+            let byte_size = buffer.read_u16(4, little_endian);
+            let zero = buffer.read_u16(6, little_endian);
+            if byte_size != 8 || zero != 0 {
+                return Err(ImageInfoError::UnrecognizedFormat);
+            }
+            let buffer = ri.read(base + 8, 8)?;
+            Ok(IfdReader {
+                base,
+                end,
+                little_endian,
+                big_tiff: true,
+                first_ifd: buffer.read_u64(0, little_endian),
+            })
+        } else {
+            Err(ImageInfoError::UnrecognizedFormat)
+        }
+    }
+
+    fn contains(&self, offset: u64, size: u64) -> bool {
+        matches!(offset.checked_add(size), Some(end) if end <= self.end as u64)
+    }
+
+    // Entries lying beyond the end of data are dropped, and so is the next IFD
+    // offset in that case.
+    pub fn read_ifd<R>(&self, ri: &mut ReadInterface<R>, offset: u64) -> ImageInfoResult<Ifd>
+    where
+        R: BufRead + Seek,
+    {
+        let (count_size, entry_size, value_size) = if self.big_tiff {
+            (8u64, 20u64, 8u64)
+        } else {
+            (2u64, 12u64, 4u64)
+        };
+        let start = (self.base as u64).saturating_add(offset);
+        if !self.contains(start, count_size) {
+            return Err(ImageInfoError::UnrecognizedFormat);
+        }
+        let buffer = ri.read(start as usize, count_size as usize)?;
+        let entry_count = if self.big_tiff {
+            buffer.read_u64(0, self.little_endian)
+        } else {
+            buffer.read_u16(0, self.little_endian) as u64
+        };
+
+        let entries_start = start + count_size;
+        let available = (self.end as u64 - entries_start) / entry_size;
+        let entry_count_fit = min(min(entry_count, available), MAX_IFD_ENTRIES);
+        let buffer = ri.read(
+            entries_start as usize,
+            (entry_count_fit * entry_size) as usize,
+        )?;
+
+        let mut ifd = Ifd {
+            entries: Vec::with_capacity(entry_count_fit as usize),
+            next: 0,
+        };
+        for i in 0..entry_count_fit as usize {
+            let entry_offset = i * entry_size as usize;
+            let tag = buffer.read_u16(entry_offset, self.little_endian);
+            let field_type = buffer.read_u16(entry_offset + 2, self.little_endian);
+            let (count, value_offset) = if self.big_tiff {
+                (buffer.read_u64(entry_offset + 4, self.little_endian), 12)
+            } else {
+                (
+                    buffer.read_u32(entry_offset + 4, self.little_endian) as u64,
+                    8,
+                )
+            };
+            let data_size = field_type_size(field_type).saturating_mul(count);
+            let data_offset = if data_size <= value_size {
+                entries_start + (entry_offset + value_offset) as u64
+            } else if self.big_tiff {
+                (self.base as u64)
+                    .saturating_add(buffer.read_u64(entry_offset + 12, self.little_endian))
+            } else {
+                self.base as u64 + buffer.read_u32(entry_offset + 8, self.little_endian) as u64
+            };
+            ifd.entries.push(IfdEntry {
+                tag,
+                field_type,
+                count,
+                data_offset,
+            });
+        }
+
+        let next_offset = entries_start.saturating_add(entry_count.saturating_mul(entry_size));
+        if entry_count_fit == entry_count && self.contains(next_offset, value_size) {
+            let buffer = ri.read(next_offset as usize, value_size as usize)?;
+            ifd.next = if self.big_tiff {
+                buffer.read_u64(0, self.little_endian)
+            } else {
+                buffer.read_u32(0, self.little_endian) as u64
+            };
+        }
+        Ok(ifd)
+    }
+
+    // The given IFD and all IFDs linked from it, stops at loops and broken links
+    pub fn read_ifd_chain<R>(
+        &self,
+        ri: &mut ReadInterface<R>,
+        offset: u64,
+    ) -> ImageInfoResult<Vec<Ifd>>
+    where
+        R: BufRead + Seek,
+    {
+        let mut visited = HashSet::new();
+        visited.insert(offset);
+        let mut chain = vec![self.read_ifd(ri, offset)?];
+        while chain.len() < MAX_IFD_CHAIN_LENGTH {
+            let next = chain[chain.len() - 1].next;
+            if next == 0 || !visited.insert(next) {
+                break;
+            }
+            match self.read_ifd(ri, next) {
+                Ok(ifd) => chain.push(ifd),
+                Err(ImageInfoError::UnrecognizedFormat) => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(chain)
+    }
+
+    fn read_value<R>(
+        &self,
+        ri: &mut ReadInterface<R>,
+        entry: &IfdEntry,
+        index: u64,
+    ) -> ImageInfoResult<Option<RawBuffer>>
+    where
+        R: BufRead + Seek,
+    {
+        let size = field_type_size(entry.field_type);
+        if size == 0 || index >= entry.count {
+            return Ok(None);
+        }
+        let offset = entry.data_offset.saturating_add(index.saturating_mul(size));
+        if !self.contains(offset, size) {
+            return Ok(None);
+        }
+        Ok(Some(ri.read(offset as usize, size as usize)?))
+    }
+
+    // Unsigned integer value of BYTE, SHORT, LONG, IFD, LONG8 and IFD8 entries
+    pub fn read_uint<R>(
+        &self,
+        ri: &mut ReadInterface<R>,
+        entry: &IfdEntry,
+        index: u64,
+    ) -> ImageInfoResult<Option<u64>>
+    where
+        R: BufRead + Seek,
+    {
+        if !matches!(entry.field_type, 1 | 3 | 4 | 7 | 13 | 16 | 18) {
+            return Ok(None);
+        }
+        Ok(self
+            .read_value(ri, entry, index)?
+            .map(|buffer| match buffer.len() {
+                1 => buffer.read_u8(0) as u64,
+                2 => buffer.read_u16(0, self.little_endian) as u64,
+                4 => buffer.read_u32(0, self.little_endian) as u64,
+                _ => buffer.read_u64(0, self.little_endian),
+            }))
+    }
+
+    // Numerator and denominator of RATIONAL entries
+    pub fn read_rational<R>(
+        &self,
+        ri: &mut ReadInterface<R>,
+        entry: &IfdEntry,
+        index: u64,
+    ) -> ImageInfoResult<Option<(u32, u32)>>
+    where
+        R: BufRead + Seek,
+    {
+        if entry.field_type != 5 {
+            return Ok(None);
+        }
+        Ok(self.read_value(ri, entry, index)?.map(|buffer| {
+            (
+                buffer.read_u32(0, self.little_endian),
+                buffer.read_u32(4, self.little_endian),
+            )
+        }))
+    }
+
+    // ASCII entries, without the terminating NUL and trailing padding
+    pub fn read_ascii<R>(
+        &self,
+        ri: &mut ReadInterface<R>,
+        entry: &IfdEntry,
+    ) -> ImageInfoResult<Option<String>>
+    where
+        R: BufRead + Seek,
+    {
+        if entry.field_type != 2 || entry.count == 0 {
+            return Ok(None);
+        }
+        let size = min(entry.count, MAX_ASCII_LENGTH);
+        if !self.contains(entry.data_offset, size) {
+            return Ok(None);
+        }
+        let buffer = ri.read(entry.data_offset as usize, size as usize)?;
+        let end = buffer
+            .data
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(buffer.len());
+        Ok(Some(buffer.read_str(0, end).trim_end().to_string()))
+    }
+}
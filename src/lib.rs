@@ -1,11 +1,74 @@
+mod analysis;
 mod defs;
+mod details;
+mod exif;
+mod extension_check;
+mod format;
 mod formats;
+mod hdr;
+mod heif;
+mod ifd;
+mod policy;
+mod preview;
 mod raw_buffer;
 mod read_interface;
+mod sniff;
 
+pub use analysis::Analysis;
+pub use analysis::TrailingData;
 pub use defs::ImageInfoError;
 pub use defs::ImageInfoResult;
 pub use defs::ImageSize;
+pub use details::BmpCompression;
+pub use details::BmpDetails;
+pub use details::BmpHeaderVersion;
+pub use details::BmpIccProfile;
+pub use details::CursorHotspot;
+pub use details::GifDetails;
+pub use details::GifFrame;
+pub use details::HeifAuxiliaryItem;
+pub use details::HeifAuxiliaryKind;
+pub use details::HeifDetails;
+pub use details::HeifGrid;
+pub use details::HeifImageItem;
+pub use details::IcnsDetails;
+pub use details::IcnsEntry;
+pub use details::IcnsPayload;
+pub use details::IcoDetails;
+pub use details::IcoEntry;
+pub use details::IcoPayload;
+pub use details::ImageDetails;
+pub use details::Jpeg2000Component;
+pub use details::Jpeg2000Details;
+pub use details::Jpeg2000ReaderRequirements;
+pub use details::Jpeg2000StandardFeature;
+pub use details::JpegCodingProcess;
+pub use details::JpegComponent;
+pub use details::JpegDetails;
+pub use details::JpegQuantizationTable;
+pub use details::PngChromaticities;
+pub use details::PngChunk;
+pub use details::PngDetails;
+pub use details::PngText;
+pub use details::PngTime;
+pub use details::PsdColorMode;
+pub use details::PsdDetails;
+pub use details::PsdImageResource;
+pub use details::QoiColorspace;
+pub use details::QoiDetails;
+pub use details::TgaColorMap;
+pub use details::TgaDetails;
+pub use details::TgaExtension;
+pub use details::TgaOrigin;
+pub use details::TiffDetails;
+pub use details::TiffPage;
+pub use details::WebpChunk;
+pub use details::WebpCompression;
+pub use details::WebpDetails;
+pub use exif::Exif;
+pub use exif::GpsCoordinates;
+pub use exif::Rational;
+pub use extension_check::ExtensionCheck;
 use formats::try_avif_heic;
 use formats::try_bmp;
 use formats::try_cur_ico;
@@ -23,16 +86,31 @@ use formats::try_qoi;
 use formats::try_tga;
 use formats::try_tiff;
 use formats::try_webp;
+pub use hdr::Cicp;
+pub use hdr::GainMap;
+pub use hdr::HdrMetadata;
+pub use hdr::RadianceHeader;
+pub use policy::DecodeEstimate;
+pub use policy::DecodePolicy;
+pub use policy::PolicyViolation;
+pub use preview::Preview;
+pub use preview::PreviewKind;
 use raw_buffer::RawBuffer;
 use read_interface::ReadInterface;
+pub use sniff::Sniff;
+pub use sniff::SniffCandidate;
+pub use sniff::SniffConfidence;
+pub use sniff::SNIFF_LENGTH;
 use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Cursor, Seek, SeekFrom};
 use std::path::Path;
 
-use serde::Serialize;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ImageFormat {
     AVIF,
     HEIC,
@@ -57,7 +135,9 @@ pub enum ImageFormat {
     TGA,
 }
 
-#[derive(Debug, PartialEq, Serialize)]
+// Deserialize is implemented in format.rs
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct ImageInfo {
     pub format: ImageFormat,
     pub ext: &'static str,
@@ -69,6 +149,68 @@ pub struct ImageInfo {
 
 type Detector<R> = fn(&mut ReadInterface<R>, usize) -> ImageInfoResult<ImageInfo>;
 
+fn detectors<R>() -> [(ImageFormat, Detector<R>); 21]
+where
+    R: BufRead + Seek,
+{
+    [
+        (ImageFormat::AVIF, try_avif_heic),
+        (ImageFormat::HEIC, try_avif_heic),
+        (ImageFormat::BMP, try_bmp),
+        (ImageFormat::CUR, try_cur_ico),
+        (ImageFormat::ICO, try_cur_ico),
+        (ImageFormat::DDS, try_dds),
+        (ImageFormat::GIF, try_gif),
+        (ImageFormat::HDR, try_hdr),
+        (ImageFormat::ICNS, try_icns),
+        (ImageFormat::J2K, try_jpeg2000_code_stream),
+        (ImageFormat::JP2, try_jpeg2000),
+        (ImageFormat::JPH, try_jpeg2000),
+        (ImageFormat::JPX, try_jpeg2000),
+        (ImageFormat::JPEG, try_jpg),
+        (ImageFormat::KTX, try_ktx),
+        (ImageFormat::PNG, try_png),
+        (ImageFormat::PSD, try_psd),
+        (ImageFormat::QOI, try_qoi),
+        (ImageFormat::TIFF, try_tiff),
+        (ImageFormat::WEBP, try_webp),
+        // !!! keep tga last !!!
+        (ImageFormat::TGA, try_tga),
+    ]
+}
+
+// Every detector that accepts the data, in detection order. Detectors shared
+// by several formats are only tried once
+fn detect_iter<'a, R>(
+    ri: &'a mut ReadInterface<R>,
+    length: usize,
+) -> impl Iterator<Item = ImageInfo> + 'a
+where
+    R: BufRead + Seek + 'a,
+{
+    let mut tried: HashSet<Detector<R>> = HashSet::new();
+    // by value, array.into_iter() iterates references in edition 2018
+    IntoIterator::into_iter(detectors())
+        .filter(move |d| tried.insert(d.1))
+        .filter_map(move |d| d.1(ri, length).ok())
+}
+
+fn detect<R>(ri: &mut ReadInterface<R>, length: usize) -> ImageInfoResult<ImageInfo>
+where
+    R: BufRead + Seek,
+{
+    detect_iter(ri, length)
+        .next()
+        .ok_or(ImageInfoError::UnrecognizedFormat)
+}
+
+fn detect_all<R>(ri: &mut ReadInterface<R>, length: usize) -> Vec<ImageInfo>
+where
+    R: BufRead + Seek,
+{
+    detect_iter(ri, length).collect()
+}
+
 impl ImageInfo {
     pub fn from_reader<R>(reader: &mut R) -> ImageInfoResult<ImageInfo>
     where
@@ -76,49 +218,7 @@ impl ImageInfo {
     {
         let length = reader.seek(SeekFrom::End(0))? as usize;
         let mut ri = ReadInterface::from_reader(reader, length);
-
-        let dl: [(ImageFormat, Detector<_>); 21] = [
-            (ImageFormat::AVIF, try_avif_heic),
-            (ImageFormat::HEIC, try_avif_heic),
-            (ImageFormat::BMP, try_bmp),
-            (ImageFormat::CUR, try_cur_ico),
-            (ImageFormat::ICO, try_cur_ico),
-            (ImageFormat::DDS, try_dds),
-            (ImageFormat::GIF, try_gif),
-            (ImageFormat::HDR, try_hdr),
-            (ImageFormat::ICNS, try_icns),
-            (ImageFormat::J2K, try_jpeg2000_code_stream),
-            (ImageFormat::JP2, try_jpeg2000),
-            (ImageFormat::JPH, try_jpeg2000),
-            (ImageFormat::JPX, try_jpeg2000),
-            (ImageFormat::JPEG, try_jpg),
-            (ImageFormat::KTX, try_ktx),
-            (ImageFormat::PNG, try_png),
-            (ImageFormat::PSD, try_psd),
-            (ImageFormat::QOI, try_qoi),
-            (ImageFormat::TIFF, try_tiff),
-            (ImageFormat::WEBP, try_webp),
-            // !!! keep tga last !!!
-            (ImageFormat::TGA, try_tga),
-        ];
-
-        let mut tried: HashSet<&Detector<_>> = HashSet::new();
-
-        // let dm: HashMap<ImageFormat, Detector<_>> = dl.iter().cloned().collect();
-
-        for d in dl.iter() {
-            // let format = &d.0;
-            let detector = &d.1;
-            if tried.contains(detector) {
-                continue;
-            }
-            tried.insert(detector);
-            if let Ok(image_info) = detector(&mut ri, length) {
-                return Ok(image_info);
-            }
-        }
-
-        Err(ImageInfoError::UnrecognizedFormat)
+        detect(&mut ri, length)
     }
 
     pub fn from_file(file: &File) -> ImageInfoResult<ImageInfo> {
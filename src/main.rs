@@ -1,7 +1,37 @@
-use imageinfo::ImageInfo;
+use imageinfo::{ExtensionCheck, ImageInfo};
+
+// Exits with 1 if an extension doesn't match the content or a file can't be
+// checked
+fn check(filepaths: &[String]) -> i32 {
+    let mut status = 0;
+    for filepath in filepaths {
+        match ExtensionCheck::from_file_path(filepath) {
+            Ok(check) if check.matches => {
+                println!("{}: ok ({})", filepath, check.format);
+            }
+            Ok(check) => {
+                eprintln!(
+                    "{}: mismatch, content is {}, expected extension .{}",
+                    filepath, check.format, check.suggested_extension
+                );
+                status = 1;
+            }
+            Err(err) => {
+                eprintln!("{}: error, {}", filepath, err);
+                status = 1;
+            }
+        }
+    }
+    status
+}
 
 fn main() {
-    for filepath in std::env::args().skip(1) {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--check") {
+        std::process::exit(check(&args[1..]));
+    }
+
+    for filepath in args {
         println!("File: {}", filepath);
         match ImageInfo::from_file_path(&filepath) {
             Ok(info) => {
@@ -0,0 +1,377 @@
+use crate::details::read_details;
+use crate::formats::{read_dds_mip_count, read_ktx_levels};
+use crate::{
+    detect, ImageDetails, ImageFormat, ImageInfoError, ImageInfoResult, ImageSize, ReadInterface,
+};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Seek, SeekFrom};
+use std::path::Path;
+
+// Memory a decoder needs for the full image, from the header fields only
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DecodeEstimate {
+    pub format: ImageFormat,
+    pub size: ImageSize,
+    // per pixel once decoded, e.g. 4 for RGBA or palette images with alpha
+    pub channels: u8,
+    pub bytes_per_channel: u8,
+    // frames, layers, pages, entries or mip levels, 1 for a single image
+    pub frames: u64,
+    // summed over the frames
+    pub pixels: u64,
+    pub bytes: u64,
+    // the largest of any frame, page, entry or codestream, so that a small
+    // first image can't hide a huge one
+    pub largest_width: u64,
+    pub largest_height: u64,
+    pub largest_frame_pixels: u64,
+    // longer side over shorter side, 1 for an empty image
+    pub largest_aspect_ratio: f64,
+}
+
+// A value equal to its limit passes, None means unlimited
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DecodePolicy {
+    // width or height of any frame
+    pub max_dimension: Option<u64>,
+    // width times height of any frame
+    pub max_pixels: Option<u64>,
+    pub max_frames: Option<u64>,
+    // longer side over shorter side of any frame
+    pub max_aspect_ratio: Option<f64>,
+    // estimated decoded bytes
+    pub max_bytes: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum PolicyViolation {
+    Dimension { width: u64, height: u64, max: u64 },
+    Pixels { pixels: u64, max: u64 },
+    Frames { frames: u64, max: u64 },
+    AspectRatio { ratio: f64, max: f64 },
+    Bytes { bytes: u64, max: u64 },
+}
+
+impl std::error::Error for PolicyViolation {}
+
+impl std::fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Dimension { width, height, max } => f.write_fmt(format_args!(
+                "image size {}x{} exceeds the maximum dimension {}",
+                width, height, max
+            )),
+            Self::Pixels { pixels, max } => {
+                f.write_fmt(format_args!("{} pixels exceed the maximum {}", pixels, max))
+            }
+            Self::Frames { frames, max } => {
+                f.write_fmt(format_args!("{} frames exceed the maximum {}", frames, max))
+            }
+            Self::AspectRatio { ratio, max } => f.write_fmt(format_args!(
+                "aspect ratio {:.2} exceeds the maximum {:.2}",
+                ratio, max
+            )),
+            Self::Bytes { bytes, max } => f.write_fmt(format_args!(
+                "estimated {} decoded bytes exceed the maximum {}",
+                bytes, max
+            )),
+        }
+    }
+}
+
+fn dimensions(size: &ImageSize) -> (u64, u64) {
+    (size.width.max(0) as u64, size.height.max(0) as u64)
+}
+
+fn pixel_count(size: &ImageSize) -> u64 {
+    let (width, height) = dimensions(size);
+    width.saturating_mul(height)
+}
+
+fn sum_pixels(sizes: &[(u64, u64)]) -> u64 {
+    sizes.iter().fold(0u64, |sum, &(width, height)| {
+        sum.saturating_add(width.saturating_mul(height))
+    })
+}
+
+// Pixels of a chain of levels, each half the previous one
+fn mip_chain_pixels(size: &ImageSize, levels: u32) -> u64 {
+    let (mut width, mut height) = (size.width.max(0) as u64, size.height.max(0) as u64);
+    let mut pixels = 0u64;
+    // no valid chain is longer, the count comes from the header
+    for _ in 0..levels.min(64) {
+        pixels = pixels.saturating_add(width.saturating_mul(height));
+        width = (width / 2).max(1);
+        height = (height / 2).max(1);
+    }
+    pixels
+}
+
+// Channels and bytes per channel once decoded
+fn pixel_layout(details: &Option<ImageDetails>, format: ImageFormat) -> (u8, u8) {
+    match details {
+        Some(ImageDetails::Bmp(bmp)) => (if bmp.bit_count == 32 { 4 } else { 3 }, 1),
+        Some(ImageDetails::Jpeg(jpeg)) => {
+            let channels = jpeg.components.len() as u8;
+            (channels, if jpeg.precision > 8 { 2 } else { 1 })
+        }
+        Some(ImageDetails::Jpeg2000(j2k)) => {
+            let depth = j2k
+                .components
+                .iter()
+                .map(|c| c.bit_depth)
+                .max()
+                .unwrap_or(8);
+            // Csiz goes up to 16384
+            let channels = j2k.components.len().min(255) as u8;
+            (channels, if depth > 8 { 2 } else { 1 })
+        }
+        Some(ImageDetails::Png(png)) => {
+            let channels = match png.color_type {
+                0 => 1,
+                4 => 2,
+                2 => 3,
+                // palette entries expand to RGB
+                3 => 3,
+                _ => 4,
+            };
+            // tRNS adds an alpha channel
+            let channels = if png.has_transparency && channels % 2 == 1 {
+                channels + 1
+            } else {
+                channels
+            };
+            (channels, if png.bit_depth == 16 { 2 } else { 1 })
+        }
+        Some(ImageDetails::Psd(psd)) => (psd.channels.min(255) as u8, (psd.depth / 8).max(1) as u8),
+        Some(ImageDetails::Qoi(qoi)) => (qoi.channels, 1),
+        Some(ImageDetails::Tga(tga)) => match tga.image_type {
+            3 | 11 => (1, 1),
+            _ if tga.alpha_bits > 0 => (4, 1),
+            _ => (3, 1),
+        },
+        Some(ImageDetails::Webp(webp)) => (if webp.has_alpha { 4 } else { 3 }, 1),
+        // 32-bit float RGB
+        _ if format == ImageFormat::HDR => (3, 4),
+        _ => (4, 1),
+    }
+}
+
+impl DecodeEstimate {
+    pub fn from_reader<R>(reader: &mut R) -> ImageInfoResult<DecodeEstimate>
+    where
+        R: BufRead + Seek,
+    {
+        let length = reader.seek(SeekFrom::End(0))? as usize;
+        let mut ri = ReadInterface::from_reader(reader, length);
+        let info = detect(&mut ri, length)?;
+        // the size alone still gives an estimate
+        let details = match read_details(&mut ri, length, info.format) {
+            Err(ImageInfoError::UnrecognizedFormat) => None,
+            details => details?,
+        };
+        let canvas = pixel_count(&info.size);
+        let (canvas_width, canvas_height) = dimensions(&info.size);
+        // sizes of the frames, pages or entries that a decoder allocates
+        let mut frame_sizes = vec![(canvas_width, canvas_height)];
+
+        let (frames, pixels) = match (&details, info.format) {
+            // at least the canvas, frames may reach past it
+            (Some(ImageDetails::Gif(gif)), _) => {
+                let sizes: Vec<(u64, u64)> = gif
+                    .frames
+                    .iter()
+                    .map(|frame| {
+                        (
+                            canvas_width.max(frame.left as u64 + frame.width as u64),
+                            canvas_height.max(frame.top as u64 + frame.height as u64),
+                        )
+                    })
+                    .collect();
+                let pixels = if sizes.is_empty() {
+                    canvas
+                } else {
+                    sum_pixels(&sizes)
+                };
+                let frames = sizes.len().max(1) as u64;
+                frame_sizes.extend(sizes);
+                (frames, pixels)
+            }
+            (Some(ImageDetails::Png(png)), _) => {
+                let position = |chunk_type: &str| {
+                    png.chunks
+                        .iter()
+                        .position(|chunk| chunk.chunk_type == chunk_type)
+                };
+                let mut sizes = vec![];
+                // an IDAT before the first fcTL is a default image outside
+                // the animation, decoded all the same
+                if let (Some(idat), Some(fctl)) = (position("IDAT"), position("fcTL")) {
+                    if idat < fctl {
+                        sizes.push((canvas_width, canvas_height));
+                    }
+                }
+                // fcTL: sequence number, width, height, x and y offsets
+                for chunk in png.chunks.iter().filter(|c| c.chunk_type == "fcTL") {
+                    if chunk.length < 20 {
+                        sizes.push((canvas_width, canvas_height));
+                        continue;
+                    }
+                    let buffer = ri.read(chunk.offset as usize + 8, 20)?;
+                    let right = buffer.read_u32_be(12) as u64 + buffer.read_u32_be(4) as u64;
+                    let bottom = buffer.read_u32_be(16) as u64 + buffer.read_u32_be(8) as u64;
+                    sizes.push((canvas_width.max(right), canvas_height.max(bottom)));
+                }
+                let pixels = if sizes.is_empty() {
+                    canvas
+                } else {
+                    sum_pixels(&sizes)
+                };
+                let frames = sizes.len().max(1) as u64;
+                frame_sizes.extend(sizes);
+                (frames, pixels)
+            }
+            (Some(ImageDetails::Webp(webp)), _) => {
+                let frames = webp
+                    .chunks
+                    .iter()
+                    .filter(|c| c.chunk_type == "ANMF")
+                    .count();
+                let frames = frames.max(1) as u64;
+                (frames, canvas.saturating_mul(frames))
+            }
+            // the composite image, then each layer at most the canvas size
+            (Some(ImageDetails::Psd(psd)), _) => {
+                let frames = 1 + psd.layer_count.unwrap_or(0) as u64;
+                (frames, canvas.saturating_mul(frames))
+            }
+            (Some(ImageDetails::Tiff(tiff)), _) => {
+                let sizes: Vec<(u64, u64)> = tiff
+                    .pages
+                    .iter()
+                    .map(|page| dimensions(&page.size))
+                    .collect();
+                let pixels = sum_pixels(&sizes);
+                frame_sizes.extend(sizes);
+                (tiff.page_count().max(1) as u64, pixels)
+            }
+            // mip levels are at most the size of the first one
+            (_, ImageFormat::DDS) => {
+                let levels = read_dds_mip_count(&mut ri, length)?;
+                (levels as u64, mip_chain_pixels(&info.size, levels))
+            }
+            (_, ImageFormat::KTX) => {
+                let (levels, layers) = read_ktx_levels(&mut ri, length)?;
+                // a full chain down to 1x1 is generated for 0
+                let levels = if levels == 0 {
+                    let longest = info.size.width.max(info.size.height).max(1) as u64;
+                    64 - longest.leading_zeros()
+                } else {
+                    levels
+                };
+                let pixels = mip_chain_pixels(&info.size, levels).saturating_mul(layers as u64);
+                ((levels as u64).saturating_mul(layers as u64), pixels)
+            }
+            // icons and codestreams
+            _ if !info.entry_sizes.is_empty() => {
+                let sizes: Vec<(u64, u64)> = info.entry_sizes.iter().map(dimensions).collect();
+                let pixels = sum_pixels(&sizes);
+                frame_sizes.extend(sizes);
+                (info.entry_sizes.len() as u64, pixels)
+            }
+            _ => (1, canvas),
+        };
+
+        let largest_width = frame_sizes.iter().map(|size| size.0).max().unwrap_or(0);
+        let largest_height = frame_sizes.iter().map(|size| size.1).max().unwrap_or(0);
+        let largest_frame_pixels = frame_sizes
+            .iter()
+            .map(|&(width, height)| width.saturating_mul(height))
+            .max()
+            .unwrap_or(0);
+        let largest_aspect_ratio = frame_sizes
+            .iter()
+            .filter(|&&(width, height)| width > 0 && height > 0)
+            .map(|&(width, height)| width.max(height) as f64 / width.min(height) as f64)
+            .fold(1.0, f64::max);
+
+        let (channels, bytes_per_channel) = pixel_layout(&details, info.format);
+        let bytes = pixels
+            .saturating_mul(channels as u64)
+            .saturating_mul(bytes_per_channel as u64);
+        Ok(DecodeEstimate {
+            format: info.format,
+            size: info.size,
+            channels,
+            bytes_per_channel,
+            frames,
+            pixels,
+            bytes,
+            largest_width,
+            largest_height,
+            largest_frame_pixels,
+            largest_aspect_ratio,
+        })
+    }
+
+    pub fn from_file(file: &File) -> ImageInfoResult<DecodeEstimate> {
+        let mut reader = BufReader::new(file);
+        Self::from_reader(&mut reader)
+    }
+
+    pub fn from_file_path(filepath: impl AsRef<Path>) -> ImageInfoResult<DecodeEstimate> {
+        let file = File::open(filepath)?;
+        Self::from_file(&file)
+    }
+
+    pub fn from_raw_data(data: &[u8]) -> ImageInfoResult<DecodeEstimate> {
+        let mut reader = BufReader::new(Cursor::new(data));
+        Self::from_reader(&mut reader)
+    }
+}
+
+impl DecodePolicy {
+    // The first limit exceeded, in field order
+    pub fn check(&self, estimate: &DecodeEstimate) -> Result<(), PolicyViolation> {
+        if let Some(max) = self.max_dimension {
+            let (width, height) = (estimate.largest_width, estimate.largest_height);
+            if width > max || height > max {
+                return Err(PolicyViolation::Dimension { width, height, max });
+            }
+        }
+        if let Some(max) = self.max_pixels {
+            let pixels = estimate.largest_frame_pixels;
+            if pixels > max {
+                return Err(PolicyViolation::Pixels { pixels, max });
+            }
+        }
+        if let Some(max) = self.max_frames {
+            if estimate.frames > max {
+                return Err(PolicyViolation::Frames {
+                    frames: estimate.frames,
+                    max,
+                });
+            }
+        }
+        if let Some(max) = self.max_aspect_ratio {
+            let ratio = estimate.largest_aspect_ratio;
+            if ratio > max {
+                return Err(PolicyViolation::AspectRatio { ratio, max });
+            }
+        }
+        if let Some(max) = self.max_bytes {
+            if estimate.bytes > max {
+                return Err(PolicyViolation::Bytes {
+                    bytes: estimate.bytes,
+                    max,
+                });
+            }
+        }
+        Ok(())
+    }
+}
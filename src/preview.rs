@@ -0,0 +1,412 @@
+use crate::exif::find_exif;
+use crate::formats::{read_psd_resources, try_jpeg2000, try_jpg, try_png};
+use crate::heif::read_heif_meta;
+use crate::ifd::{Ifd, IfdReader};
+use crate::{
+    detect, Detector, ImageFormat, ImageInfo, ImageInfoError, ImageInfoResult, ImageSize,
+    ReadInterface,
+};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Seek, SeekFrom};
+use std::path::Path;
+
+const TAG_NEW_SUBFILE_TYPE: u16 = 254;
+const TAG_IMAGE_WIDTH: u16 = 256;
+const TAG_IMAGE_LENGTH: u16 = 257;
+const TAG_STRIP_OFFSETS: u16 = 273;
+const TAG_STRIP_BYTE_COUNTS: u16 = 279;
+const TAG_SUB_IFDS: u16 = 330;
+const TAG_JPEG_INTERCHANGE_FORMAT: u16 = 513;
+const TAG_JPEG_INTERCHANGE_FORMAT_LENGTH: u16 = 514;
+
+// Upper bound of SubIFDs followed from a single IFD
+const MAX_SUB_IFDS: u64 = 64;
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum PreviewKind {
+    // JPEGInterchangeFormat of EXIF IFD1
+    ExifThumbnail,
+    // Reduced resolution image of a TIFF based file, e.g. preview SubIFDs of RAW files
+    TiffReducedImage,
+    // Item referring to the primary item with a thmb reference
+    HeifThumbnail,
+    // Image resource 1036
+    PsdThumbnail,
+    // Largest PNG entry
+    IcnsImage,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Preview {
+    pub kind: PreviewKind,
+    pub offset: u64,
+    pub length: u64,
+    // None if the data is not a standalone image file, e.g. a HEVC coded HEIF item
+    pub format: Option<ImageFormat>,
+    pub size: Option<ImageSize>,
+}
+
+impl Preview {
+    // Embedded previews in file order, offsets are absolute so that
+    // callers can range-read them without touching the rest of the file
+    pub fn from_reader<R>(reader: &mut R) -> ImageInfoResult<Vec<Preview>>
+    where
+        R: BufRead + Seek,
+    {
+        let length = reader.seek(SeekFrom::End(0))? as usize;
+        let mut ri = ReadInterface::from_reader(reader, length);
+        let info = detect(&mut ri, length)?;
+
+        let mut previews = vec![];
+        match info.format {
+            ImageFormat::JPEG | ImageFormat::PNG | ImageFormat::WEBP => {
+                find_exif_thumbnail(&mut ri, length, &info.format, &mut previews)?;
+            }
+            ImageFormat::AVIF | ImageFormat::HEIC => {
+                find_heif_thumbnails(&mut ri, length, &mut previews)?;
+                find_exif_thumbnail(&mut ri, length, &info.format, &mut previews)?;
+            }
+            ImageFormat::TIFF => find_tiff_reduced_images(&mut ri, length, &mut previews)?,
+            ImageFormat::PSD => find_psd_thumbnail(&mut ri, length, &mut previews)?,
+            ImageFormat::ICNS => find_icns_image(&mut ri, length, &mut previews)?,
+            _ => {}
+        }
+        Ok(previews)
+    }
+
+    pub fn from_file(file: &File) -> ImageInfoResult<Vec<Preview>> {
+        let mut reader = BufReader::new(file);
+        Self::from_reader(&mut reader)
+    }
+
+    pub fn from_file_path(filepath: impl AsRef<Path>) -> ImageInfoResult<Vec<Preview>> {
+        let file = File::open(filepath)?;
+        Self::from_file(&file)
+    }
+
+    pub fn from_raw_data(data: &[u8]) -> ImageInfoResult<Vec<Preview>> {
+        let mut reader = BufReader::new(Cursor::new(data));
+        Self::from_reader(&mut reader)
+    }
+}
+
+// Previews are jpeg, png or jpeg2000 (icns), other data is left undetected
+pub fn probe_embedded<R>(
+    ri: &mut ReadInterface<R>,
+    offset: usize,
+    length: usize,
+) -> ImageInfoResult<Option<ImageInfo>>
+where
+    R: BufRead + Seek,
+{
+    let mut sub = ri.sub_interface(offset, length);
+    let detectors: [Detector<_>; 3] = [try_jpg, try_png, try_jpeg2000];
+    for detector in detectors.iter() {
+        match detector(&mut sub, length) {
+            Ok(info) => return Ok(Some(info)),
+            Err(ImageInfoError::UnrecognizedFormat) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(None)
+}
+
+fn push_preview<R>(
+    ri: &mut ReadInterface<R>,
+    previews: &mut Vec<Preview>,
+    kind: PreviewKind,
+    offset: usize,
+    length: usize,
+    size: Option<ImageSize>,
+) -> ImageInfoResult<()>
+where
+    R: BufRead + Seek,
+{
+    let (format, size) = match probe_embedded(ri, offset, length)? {
+        Some(info) => (Some(info.format), Some(info.size)),
+        None => (None, size),
+    };
+    previews.push(Preview {
+        kind,
+        offset: offset as u64,
+        length: length as u64,
+        format,
+        size,
+    });
+    Ok(())
+}
+
+fn read_tag<R>(
+    ri: &mut ReadInterface<R>,
+    reader: &IfdReader,
+    ifd: &Ifd,
+    tag: u16,
+) -> ImageInfoResult<Option<u64>>
+where
+    R: BufRead + Seek,
+{
+    match ifd.find(tag) {
+        Some(entry) => reader.read_uint(ri, entry, 0),
+        None => Ok(None),
+    }
+}
+
+// Absolute range of the JPEGInterchangeFormat data, or of the only strip
+fn find_ifd_image<R>(
+    ri: &mut ReadInterface<R>,
+    reader: &IfdReader,
+    ifd: &Ifd,
+) -> ImageInfoResult<Option<(usize, usize)>>
+where
+    R: BufRead + Seek,
+{
+    let mut range = (
+        read_tag(ri, reader, ifd, TAG_JPEG_INTERCHANGE_FORMAT)?,
+        read_tag(ri, reader, ifd, TAG_JPEG_INTERCHANGE_FORMAT_LENGTH)?,
+    );
+    if range.0.is_none() {
+        let single_strip = matches!(ifd.find(TAG_STRIP_OFFSETS), Some(entry) if entry.count == 1);
+        if single_strip {
+            range = (
+                read_tag(ri, reader, ifd, TAG_STRIP_OFFSETS)?,
+                read_tag(ri, reader, ifd, TAG_STRIP_BYTE_COUNTS)?,
+            );
+        }
+    }
+    match range {
+        (Some(offset), Some(length)) if length > 0 => {
+            let offset = (reader.base as u64).saturating_add(offset);
+            if offset.saturating_add(length) > reader.end as u64 {
+                return Ok(None);
+            }
+            Ok(Some((offset as usize, length as usize)))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn find_exif_thumbnail<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    format: &ImageFormat,
+    previews: &mut Vec<Preview>,
+) -> ImageInfoResult<()>
+where
+    R: BufRead + Seek,
+{
+    let (base, end) = match find_exif(ri, length, format) {
+        Ok(Some(range)) => range,
+        Ok(None) | Err(ImageInfoError::UnrecognizedFormat) => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    let reader = match IfdReader::new(ri, base, end) {
+        Ok(reader) => reader,
+        Err(ImageInfoError::UnrecognizedFormat) => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    let chain = match reader.read_ifd_chain(ri, reader.first_ifd) {
+        Ok(chain) => chain,
+        Err(ImageInfoError::UnrecognizedFormat) => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    if let Some(ifd1) = chain.get(1) {
+        if let Some((offset, length)) = find_ifd_image(ri, &reader, ifd1)? {
+            push_preview(
+                ri,
+                previews,
+                PreviewKind::ExifThumbnail,
+                offset,
+                length,
+                None,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn push_reduced_image<R>(
+    ri: &mut ReadInterface<R>,
+    reader: &IfdReader,
+    ifd: &Ifd,
+    previews: &mut Vec<Preview>,
+) -> ImageInfoResult<()>
+where
+    R: BufRead + Seek,
+{
+    // bit 0 of NewSubfileType marks a reduced resolution version of another image
+    let subfile_type = read_tag(ri, reader, ifd, TAG_NEW_SUBFILE_TYPE)?.unwrap_or(0);
+    if subfile_type & 1 == 0 {
+        return Ok(());
+    }
+    if let Some((offset, length)) = find_ifd_image(ri, reader, ifd)? {
+        let size = match (
+            read_tag(ri, reader, ifd, TAG_IMAGE_WIDTH)?,
+            read_tag(ri, reader, ifd, TAG_IMAGE_LENGTH)?,
+        ) {
+            (Some(width), Some(height)) => Some(ImageSize {
+                width: width as i64,
+                height: height as i64,
+            }),
+            _ => None,
+        };
+        push_preview(
+            ri,
+            previews,
+            PreviewKind::TiffReducedImage,
+            offset,
+            length,
+            size,
+        )?;
+    }
+    Ok(())
+}
+
+// https://www.awaresystems.be/imaging/tiff/tifftags/subifds.html
+fn find_tiff_reduced_images<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    previews: &mut Vec<Preview>,
+) -> ImageInfoResult<()>
+where
+    R: BufRead + Seek,
+{
+    let reader = IfdReader::new(ri, 0, length)?;
+    let chain = reader.read_ifd_chain(ri, reader.first_ifd)?;
+    for ifd in chain.iter() {
+        push_reduced_image(ri, &reader, ifd, previews)?;
+        if let Some(entry) = ifd.find(TAG_SUB_IFDS) {
+            for i in 0..entry.count.min(MAX_SUB_IFDS) {
+                let offset = match reader.read_uint(ri, entry, i)? {
+                    Some(offset) => offset,
+                    None => break,
+                };
+                match reader.read_ifd(ri, offset) {
+                    Ok(sub_ifd) => push_reduced_image(ri, &reader, &sub_ifd, previews)?,
+                    Err(ImageInfoError::UnrecognizedFormat) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn find_heif_thumbnails<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    previews: &mut Vec<Preview>,
+) -> ImageInfoResult<()>
+where
+    R: BufRead + Seek,
+{
+    let meta = match read_heif_meta(ri, length) {
+        Ok(meta) => meta,
+        Err(ImageInfoError::UnrecognizedFormat) => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    for id in meta.referring_items("thmb", meta.primary_item) {
+        let extents = match meta.items.iter().find(|item| item.id == id) {
+            Some(item) => &item.extents,
+            None => continue,
+        };
+        // only items stored in a single extent can be read in one range
+        if let [(offset, size)] = extents.as_slice() {
+            if offset.saturating_add(*size) > length as u64 || *size == 0 {
+                continue;
+            }
+            let item_size = meta.item_size(ri, id)?;
+            push_preview(
+                ri,
+                previews,
+                PreviewKind::HeifThumbnail,
+                *offset as usize,
+                *size as usize,
+                item_size,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/#50577409_71546
+fn find_psd_thumbnail<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    previews: &mut Vec<Preview>,
+) -> ImageInfoResult<()>
+where
+    R: BufRead + Seek,
+{
+    let resources = match read_psd_resources(ri, length) {
+        Ok(resources) => resources,
+        Err(ImageInfoError::UnrecognizedFormat) => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    for resource in resources {
+        // format, width, height, widthbytes, total size, compressed size, bits, planes
+        if resource.id == 1036 && resource.length > 28 {
+            let data_start = resource.offset as usize;
+            let header = ri.read(data_start, 28)?;
+            // 1 is kJpegRGB
+            if header.read_u32_be(0) == 1 {
+                let size = ImageSize {
+                    width: header.read_u32_be(4) as i64,
+                    height: header.read_u32_be(8) as i64,
+                };
+                push_preview(
+                    ri,
+                    previews,
+                    PreviewKind::PsdThumbnail,
+                    data_start + 28,
+                    resource.length as usize - 28,
+                    Some(size),
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn find_icns_image<R>(
+    ri: &mut ReadInterface<R>,
+    length: usize,
+    previews: &mut Vec<Preview>,
+) -> ImageInfoResult<()>
+where
+    R: BufRead + Seek,
+{
+    let mut largest: Option<Preview> = None;
+    let mut offset = 8usize;
+    while offset + 16 <= length {
+        let buffer = ri.read(offset, 16)?;
+        let entry_size = buffer.read_u32_be(4) as usize;
+        if entry_size < 8 || offset + entry_size > length {
+            break;
+        }
+        if buffer.cmp(8, 4, b"\x89PNG") {
+            if let Some(info) = probe_embedded(ri, offset + 8, entry_size - 8)? {
+                let area = info.size.width.saturating_mul(info.size.height);
+                let is_larger = match largest.as_ref().and_then(|preview| preview.size.as_ref()) {
+                    Some(size) => size.width.saturating_mul(size.height) < area,
+                    None => true,
+                };
+                if is_larger {
+                    largest = Some(Preview {
+                        kind: PreviewKind::IcnsImage,
+                        offset: (offset + 8) as u64,
+                        length: (entry_size - 8) as u64,
+                        format: Some(info.format),
+                        size: Some(info.size),
+                    });
+                }
+            }
+        }
+        offset += entry_size;
+    }
+    previews.extend(largest);
+    Ok(())
+}
@@ -77,6 +77,30 @@ impl RawBuffer {
         u64::from_be_bytes(self.piece(offset, size_of::<u64>()).try_into().unwrap())
     }
 
+    pub fn read_u16(&self, offset: usize, little_endian: bool) -> u16 {
+        if little_endian {
+            self.read_u16_le(offset)
+        } else {
+            self.read_u16_be(offset)
+        }
+    }
+
+    pub fn read_u32(&self, offset: usize, little_endian: bool) -> u32 {
+        if little_endian {
+            self.read_u32_le(offset)
+        } else {
+            self.read_u32_be(offset)
+        }
+    }
+
+    pub fn read_u64(&self, offset: usize, little_endian: bool) -> u64 {
+        if little_endian {
+            self.read_u64_le(offset)
+        } else {
+            self.read_u64_be(offset)
+        }
+    }
+
     pub fn read_str(&self, offset: usize, length: usize) -> String {
         String::from_utf8_lossy(self.piece(offset, length)).to_string()
     }
@@ -1,6 +1,6 @@
 use crate::{ImageInfoResult, RawBuffer};
 use std::cmp::min;
-use std::io::{BufRead, Seek, SeekFrom};
+use std::io::{BufRead, Read, Seek, SeekFrom};
 
 pub struct ReadInterface<R>
 where
@@ -84,3 +84,110 @@ where
         Ok(buffer)
     }
 }
+
+// A window of another reader, so that embedded images can be probed in place
+pub struct SubReader<'a, R>
+where
+    R: BufRead + Seek,
+{
+    reader: &'a mut R,
+    start: u64,
+    length: u64,
+    position: u64,
+}
+
+impl<'a, R> SubReader<'a, R>
+where
+    R: BufRead + Seek,
+{
+    pub fn new(reader: &'a mut R, start: u64, length: u64) -> SubReader<'a, R> {
+        SubReader {
+            reader,
+            start,
+            length,
+            position: 0,
+        }
+    }
+}
+
+impl<R> Read for SubReader<'_, R>
+where
+    R: BufRead + Seek,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let size = min(buf.len() as u64, self.length.saturating_sub(self.position)) as usize;
+        self.reader
+            .seek(SeekFrom::Start(self.start + self.position))?;
+        let size = self.reader.read(&mut buf[..size])?;
+        self.position += size as u64;
+        Ok(size)
+    }
+}
+
+impl<R> BufRead for SubReader<'_, R>
+where
+    R: BufRead + Seek,
+{
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        let remaining = self.length.saturating_sub(self.position);
+        self.reader
+            .seek(SeekFrom::Start(self.start + self.position))?;
+        let buf = self.reader.fill_buf()?;
+        let size = min(buf.len() as u64, remaining) as usize;
+        Ok(&buf[..size])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt);
+        self.position += amt as u64;
+    }
+}
+
+fn add_signed(base: u64, offset: i64) -> Option<u64> {
+    if offset >= 0 {
+        base.checked_add(offset as u64)
+    } else {
+        // wrapping keeps i64::MIN at 2^63
+        base.checked_sub(offset.wrapping_neg() as u64)
+    }
+}
+
+impl<R> Seek for SubReader<'_, R>
+where
+    R: BufRead + Seek,
+{
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => add_signed(self.length, offset),
+            SeekFrom::Current(offset) => add_signed(self.position, offset),
+        };
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+impl<R> ReadInterface<R>
+where
+    R: BufRead + Seek,
+{
+    pub fn sub_interface(
+        &mut self,
+        offset: usize,
+        length: usize,
+    ) -> ReadInterface<SubReader<'_, R>> {
+        assert!(offset + length <= self.length);
+        ReadInterface::from_reader(
+            SubReader::new(&mut self.reader, offset as u64, length as u64),
+            length,
+        )
+    }
+}
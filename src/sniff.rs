@@ -0,0 +1,231 @@
+use crate::formats::{bmp_header_version, is_valid_tga_header};
+use crate::{ImageFormat, ImageInfoError, ImageInfoResult, RawBuffer};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::cmp::{min, Reverse};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+// Sniffing never looks past this many bytes
+pub const SNIFF_LENGTH: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum SniffConfidence {
+    // a few header fields look plausible, e.g. headerless TGA
+    Low,
+    // short or shared magic, e.g. ICO, or a brand that fits several formats
+    Medium,
+    // unambiguous signature
+    High,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SniffCandidate {
+    pub format: ImageFormat,
+    pub confidence: SniffConfidence,
+}
+
+// Format guess from magic numbers only, sizes are not parsed
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Sniff {
+    // most confident first, never empty
+    pub candidates: Vec<SniffCandidate>,
+}
+
+// ISO BMFF ftyp box: AVIF and HEIC by major and compatible brands
+fn sniff_ftyp(buffer: &RawBuffer, candidates: &mut Vec<SniffCandidate>) {
+    if buffer.len() < 12 || !buffer.cmp(4, 4, b"ftyp") {
+        return;
+    }
+    let box_length = min(buffer.read_u32_be(0) as usize, buffer.len());
+    let compatible = |brand: &[u8]| {
+        (16..box_length / 4 * 4)
+            .step_by(4)
+            .any(|i| buffer.cmp(i, 4, brand))
+    };
+    let mut push = |format, confidence| candidates.push(SniffCandidate { format, confidence });
+
+    if buffer.cmp_any_of(8, 4, vec![b"avif", b"avis"]) {
+        push(ImageFormat::AVIF, SniffConfidence::High);
+    } else if buffer.cmp_any_of(8, 4, vec![b"heic", b"heix", b"hevc", b"hevx"]) {
+        push(ImageFormat::HEIC, SniffConfidence::High);
+    } else if buffer.cmp_any_of(8, 4, vec![b"mif1", b"msf1"]) {
+        // generic HEIF, the compatible brands tell which codec
+        let is_avif = compatible(b"avif") || compatible(b"avis");
+        let is_heic = ["heic", "heix", "hevc", "hevx"]
+            .iter()
+            .any(|brand| compatible(brand.as_bytes()));
+        match (is_avif, is_heic) {
+            (true, false) => push(ImageFormat::AVIF, SniffConfidence::Medium),
+            (false, true) => push(ImageFormat::HEIC, SniffConfidence::Medium),
+            _ => {
+                push(ImageFormat::AVIF, SniffConfidence::Low);
+                push(ImageFormat::HEIC, SniffConfidence::Low);
+            }
+        }
+    }
+}
+
+// JP2 signature box, then the ftyp brand
+fn sniff_jp2(buffer: &RawBuffer, candidates: &mut Vec<SniffCandidate>) {
+    if buffer.len() < 12 || !buffer.cmp(0, 12, b"\x00\x00\x00\x0CjP  \r\n\x87\n") {
+        return;
+    }
+    let formats = [
+        (ImageFormat::JP2, b"jp2 "),
+        (ImageFormat::JPH, b"jph "),
+        (ImageFormat::JPX, b"jpx "),
+    ];
+    let brand = if buffer.len() >= 24 && buffer.cmp(16, 4, b"ftyp") {
+        formats.iter().find(|(_, brand)| buffer.cmp(20, 4, *brand))
+    } else {
+        None
+    };
+    match brand {
+        Some((format, _)) => candidates.push(SniffCandidate {
+            format: *format,
+            confidence: SniffConfidence::High,
+        }),
+        None => {
+            for (format, _) in formats.iter() {
+                candidates.push(SniffCandidate {
+                    format: *format,
+                    confidence: SniffConfidence::Medium,
+                });
+            }
+        }
+    }
+}
+
+fn sniff_magic(buffer: &RawBuffer) -> Vec<SniffCandidate> {
+    let length = buffer.len();
+    let mut candidates = vec![];
+    let mut push = |format, confidence| candidates.push(SniffCandidate { format, confidence });
+
+    if length >= 8 && buffer.cmp(0, 8, b"\x89PNG\r\n\x1A\n") {
+        push(ImageFormat::PNG, SniffConfidence::High);
+    }
+    if length >= 3 && buffer.cmp(0, 3, b"\xFF\xD8\xFF") {
+        push(ImageFormat::JPEG, SniffConfidence::High);
+    } else if length >= 2 && buffer.cmp(0, 2, b"\xFF\xD8") {
+        push(ImageFormat::JPEG, SniffConfidence::Medium);
+    }
+    if length >= 6 && buffer.cmp_any_of(0, 6, vec![b"GIF87a", b"GIF89a"]) {
+        push(ImageFormat::GIF, SniffConfidence::High);
+    }
+    if length >= 12 && buffer.cmp(0, 4, b"RIFF") && buffer.cmp(8, 4, b"WEBP") {
+        push(ImageFormat::WEBP, SniffConfidence::High);
+    }
+    if length >= 18
+        && buffer.cmp(0, 2, b"BM")
+        && bmp_header_version(buffer.read_u32_le(14)).is_some()
+    {
+        push(ImageFormat::BMP, SniffConfidence::High);
+    }
+    if length >= 6 && buffer.cmp(0, 2, b"\x00\x00") && buffer.read_u16_le(4) > 0 {
+        let format = match buffer.read_u16_le(2) {
+            1 => Some(ImageFormat::ICO),
+            2 => Some(ImageFormat::CUR),
+            _ => None,
+        };
+        // reserved byte of the first entry
+        if let Some(format) = format {
+            if length < 10 || buffer.read_u8(9) == 0 {
+                push(format, SniffConfidence::Medium);
+            }
+        }
+    }
+    if length >= 4 && buffer.cmp(0, 4, b"DDS ") {
+        if length >= 8 && buffer.read_u32_le(4) == 124 {
+            push(ImageFormat::DDS, SniffConfidence::High);
+        } else {
+            push(ImageFormat::DDS, SniffConfidence::Medium);
+        }
+    }
+    if length >= 6 && buffer.cmp_any_of(0, 6, vec![b"#?RGBE", b"#?XYZE"])
+        || length >= 10 && buffer.cmp(0, 10, b"#?RADIANCE")
+    {
+        push(ImageFormat::HDR, SniffConfidence::High);
+    }
+    if length >= 8 && buffer.cmp(0, 4, b"icns") && buffer.read_u32_be(4) >= 8 {
+        push(ImageFormat::ICNS, SniffConfidence::High);
+    }
+    if length >= 4 && buffer.cmp(0, 4, b"\xFF\x4F\xFF\x51") {
+        push(ImageFormat::J2K, SniffConfidence::High);
+    }
+    if length >= 12 && buffer.cmp(0, 12, b"\xABKTX 11\xBB\r\n\x1A\n") {
+        push(ImageFormat::KTX, SniffConfidence::High);
+    }
+    if length >= 6 && buffer.cmp_any_of(0, 6, vec![b"8BPS\x00\x01", b"8BPS\x00\x02"]) {
+        push(ImageFormat::PSD, SniffConfidence::High);
+    }
+    if length >= 4 && buffer.cmp(0, 4, b"qoif") {
+        if length >= 14 && (3..=4).contains(&buffer.read_u8(12)) && buffer.read_u8(13) <= 1 {
+            push(ImageFormat::QOI, SniffConfidence::High);
+        } else {
+            push(ImageFormat::QOI, SniffConfidence::Medium);
+        }
+    }
+    if length >= 4
+        && buffer.cmp_any_of(
+            0,
+            4,
+            vec![b"II\x2A\x00", b"MM\x00\x2A", b"II\x2B\x00", b"MM\x00\x2B"],
+        )
+    {
+        push(ImageFormat::TIFF, SniffConfidence::High);
+    }
+    sniff_ftyp(buffer, &mut candidates);
+    sniff_jp2(buffer, &mut candidates);
+    // the TGA footer is at the end of the file, only the header can be checked
+    if length >= 18 && is_valid_tga_header(buffer.piece(0, 18), usize::MAX) {
+        candidates.push(SniffCandidate {
+            format: ImageFormat::TGA,
+            confidence: SniffConfidence::Low,
+        });
+    }
+
+    // stable, keeps the order above for equal confidences
+    candidates.sort_by_key(|candidate| Reverse(candidate.confidence));
+    candidates
+}
+
+impl Sniff {
+    // Most confident candidate
+    pub fn format(&self) -> ImageFormat {
+        self.candidates[0].format
+    }
+
+    // Reads at most SNIFF_LENGTH bytes from the current position, the reader
+    // doesn't need to be seekable
+    pub fn from_reader<R>(reader: &mut R) -> ImageInfoResult<Sniff>
+    where
+        R: Read,
+    {
+        let mut data = Vec::with_capacity(SNIFF_LENGTH);
+        reader.take(SNIFF_LENGTH as u64).read_to_end(&mut data)?;
+        let candidates = sniff_magic(&RawBuffer { data });
+        if candidates.is_empty() {
+            return Err(ImageInfoError::UnrecognizedFormat);
+        }
+        Ok(Sniff { candidates })
+    }
+
+    pub fn from_file(file: &File) -> ImageInfoResult<Sniff> {
+        let mut reader = BufReader::new(file);
+        Self::from_reader(&mut reader)
+    }
+
+    pub fn from_file_path(filepath: impl AsRef<Path>) -> ImageInfoResult<Sniff> {
+        let file = File::open(filepath)?;
+        Self::from_file(&file)
+    }
+
+    pub fn from_raw_data(data: &[u8]) -> ImageInfoResult<Sniff> {
+        Self::from_reader(&mut &data[..min(data.len(), SNIFF_LENGTH)])
+    }
+}
@@ -1,4 +1,19 @@
-use imageinfo::{ImageFormat, ImageInfo, ImageInfoError, ImageSize};
+// the assert macros predate this lint and are kept as they are
+#![allow(clippy::collapsible_match)]
+
+use imageinfo::{
+    Analysis, BmpCompression, BmpDetails, BmpHeaderVersion, BmpIccProfile, Cicp, CursorHotspot,
+    DecodeEstimate, DecodePolicy, Exif, ExtensionCheck, GainMap, GifDetails, GifFrame,
+    GpsCoordinates, HdrMetadata, HeifAuxiliaryItem, HeifAuxiliaryKind, HeifDetails, HeifGrid,
+    HeifImageItem, IcnsDetails, IcnsEntry, IcnsPayload, IcoDetails, IcoEntry, IcoPayload,
+    ImageDetails, ImageFormat, ImageInfo, ImageInfoError, ImageSize, Jpeg2000Component,
+    Jpeg2000Details, Jpeg2000ReaderRequirements, Jpeg2000StandardFeature, JpegCodingProcess,
+    JpegComponent, JpegDetails, JpegQuantizationTable, PngChromaticities, PngChunk, PngDetails,
+    PngText, PngTime, PolicyViolation, Preview, PreviewKind, PsdColorMode, PsdDetails,
+    PsdImageResource, QoiColorspace, QoiDetails, RadianceHeader, Rational, Sniff, SniffCandidate,
+    SniffConfidence, TgaColorMap, TgaDetails, TgaExtension, TgaOrigin, TiffDetails, TiffPage,
+    TrailingData, WebpChunk, WebpCompression, WebpDetails, SNIFF_LENGTH,
+};
 
 macro_rules! assert_eq_ok {
     ($left:expr, $right:expr $(,)?) => {{
@@ -97,6 +112,21 @@ fn test_avif() {
             entry_sizes: vec![],
         }
     );
+
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/avif/grid.avif"),
+        ImageInfo {
+            format: ImageFormat::AVIF,
+            ext: "avif",
+            full_ext: "avif",
+            mimetype: "image/avif",
+            size: ImageSize {
+                width: 700,
+                height: 900
+            },
+            entry_sizes: vec![],
+        }
+    );
 }
 
 #[test]
@@ -160,6 +190,182 @@ fn test_heic() {
             entry_sizes: vec![],
         }
     );
+
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/heic/overlay.heic"),
+        ImageInfo {
+            format: ImageFormat::HEIC,
+            ext: "heic",
+            full_ext: "heic",
+            mimetype: "image/heic",
+            size: ImageSize {
+                width: 1280,
+                height: 480
+            },
+            entry_sizes: vec![],
+        }
+    );
+
+    // unsupported iloc version, the size comes from the primary item's ispe
+    let mut data = std::fs::read("images/valid/heic/overlay.heic").unwrap();
+    data[189] = 3;
+    assert_eq_ok!(
+        ImageInfo::from_raw_data(&data),
+        ImageInfo {
+            format: ImageFormat::HEIC,
+            ext: "heic",
+            full_ext: "heic",
+            mimetype: "image/heic",
+            size: ImageSize {
+                width: 1280,
+                height: 480
+            },
+            entry_sizes: vec![],
+        }
+    );
+}
+
+#[test]
+fn test_heif_details() {
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/avif/grid.avif"),
+        Some(ImageDetails::Heif(HeifDetails {
+            primary_item: HeifImageItem {
+                id: 1,
+                item_type: "grid".to_string(),
+                size: Some(ImageSize {
+                    width: 700,
+                    height: 900
+                }),
+            },
+            grid: Some(HeifGrid {
+                rows: 2,
+                columns: 2
+            }),
+            tiles: vec![
+                HeifImageItem {
+                    id: 2,
+                    item_type: "av01".to_string(),
+                    size: Some(ImageSize {
+                        width: 512,
+                        height: 512
+                    }),
+                },
+                HeifImageItem {
+                    id: 3,
+                    item_type: "av01".to_string(),
+                    size: Some(ImageSize {
+                        width: 512,
+                        height: 512
+                    }),
+                },
+                HeifImageItem {
+                    id: 4,
+                    item_type: "av01".to_string(),
+                    size: Some(ImageSize {
+                        width: 512,
+                        height: 512
+                    }),
+                },
+                HeifImageItem {
+                    id: 5,
+                    item_type: "av01".to_string(),
+                    size: Some(ImageSize {
+                        width: 512,
+                        height: 512
+                    }),
+                }
+            ],
+            auxiliary_items: vec![
+                HeifAuxiliaryItem {
+                    item: HeifImageItem {
+                        id: 6,
+                        item_type: "av01".to_string(),
+                        size: Some(ImageSize {
+                            width: 1000,
+                            height: 800
+                        }),
+                    },
+                    kind: HeifAuxiliaryKind::Alpha,
+                    aux_type: Some("urn:mpeg:mpegB:cicp:systems:auxiliary:alpha".to_string()),
+                },
+                HeifAuxiliaryItem {
+                    item: HeifImageItem {
+                        id: 7,
+                        item_type: "av01".to_string(),
+                        size: Some(ImageSize {
+                            width: 500,
+                            height: 400
+                        }),
+                    },
+                    kind: HeifAuxiliaryKind::GainMap,
+                    aux_type: None,
+                },
+            ],
+        }))
+    );
+
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/heic/overlay.heic"),
+        Some(ImageDetails::Heif(HeifDetails {
+            primary_item: HeifImageItem {
+                id: 1,
+                item_type: "iovl".to_string(),
+                size: Some(ImageSize {
+                    width: 1280,
+                    height: 480
+                }),
+            },
+            grid: None,
+            tiles: vec![
+                HeifImageItem {
+                    id: 2,
+                    item_type: "hvc1".to_string(),
+                    size: Some(ImageSize {
+                        width: 640,
+                        height: 480
+                    }),
+                },
+                HeifImageItem {
+                    id: 3,
+                    item_type: "hvc1".to_string(),
+                    size: Some(ImageSize {
+                        width: 640,
+                        height: 480
+                    }),
+                }
+            ],
+            auxiliary_items: vec![HeifAuxiliaryItem {
+                item: HeifImageItem {
+                    id: 4,
+                    item_type: "hvc1".to_string(),
+                    size: Some(ImageSize {
+                        width: 320,
+                        height: 240
+                    }),
+                },
+                kind: HeifAuxiliaryKind::Depth,
+                aux_type: Some("urn:mpeg:hevc:2015:auxid:2".to_string()),
+            }],
+        }))
+    );
+
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/heic/sample2.heic"),
+        Some(ImageDetails::Heif(HeifDetails {
+            primary_item: HeifImageItem {
+                id: 1002,
+                item_type: "hvc1".to_string(),
+                size: Some(ImageSize {
+                    width: 1440,
+                    height: 960
+                }),
+            },
+            grid: None,
+            tiles: vec![],
+            auxiliary_items: vec![],
+        }))
+    );
 }
 
 #[test]
@@ -193,6 +399,144 @@ fn test_bmp() {
             entry_sizes: vec![],
         }
     );
+
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/bmp/os2.bmp"),
+        ImageInfo {
+            format: ImageFormat::BMP,
+            ext: "bmp",
+            full_ext: "bmp",
+            mimetype: "image/bmp",
+            size: ImageSize {
+                width: 5,
+                height: 3
+            },
+            entry_sizes: vec![],
+        }
+    );
+
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/bmp/v5-icc.bmp"),
+        ImageInfo {
+            format: ImageFormat::BMP,
+            ext: "bmp",
+            full_ext: "bmp",
+            mimetype: "image/bmp",
+            size: ImageSize {
+                width: 2,
+                height: 2
+            },
+            entry_sizes: vec![],
+        }
+    );
+
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/bmp/rle8.bmp"),
+        ImageInfo {
+            format: ImageFormat::BMP,
+            ext: "bmp",
+            full_ext: "bmp",
+            mimetype: "image/bmp",
+            size: ImageSize {
+                width: 4,
+                height: 2
+            },
+            entry_sizes: vec![],
+        }
+    );
+
+    // unknown info header size, the dimensions are read as in BITMAPINFOHEADER
+    let mut data = std::fs::read("images/valid/bmp/sample.bmp").unwrap();
+    data[14..18].copy_from_slice(&[200, 0, 0, 0]);
+    assert_eq_ok!(
+        ImageInfo::from_raw_data(&data),
+        ImageInfo {
+            format: ImageFormat::BMP,
+            ext: "bmp",
+            full_ext: "bmp",
+            mimetype: "image/bmp",
+            size: ImageSize {
+                width: 123,
+                height: 456
+            },
+            entry_sizes: vec![],
+        }
+    );
+}
+
+#[test]
+fn test_bmp_details() {
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/bmp/os2.bmp"),
+        Some(ImageDetails::Bmp(BmpDetails {
+            header_version: BmpHeaderVersion::Core,
+            header_size: 12,
+            bit_count: 4,
+            compression: BmpCompression::Rgb,
+            palette_size: 16,
+            top_down: false,
+            data_offset: 74,
+            icc_profile: None,
+        }))
+    );
+
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/bmp/rle8.bmp"),
+        Some(ImageDetails::Bmp(BmpDetails {
+            header_version: BmpHeaderVersion::Info,
+            header_size: 40,
+            bit_count: 8,
+            compression: BmpCompression::Rle8,
+            palette_size: 3,
+            top_down: false,
+            data_offset: 66,
+            icc_profile: None,
+        }))
+    );
+
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/bmp/sample2.bmp"),
+        Some(ImageDetails::Bmp(BmpDetails {
+            header_version: BmpHeaderVersion::V4,
+            header_size: 108,
+            bit_count: 24,
+            compression: BmpCompression::Rgb,
+            palette_size: 0,
+            top_down: true,
+            data_offset: 122,
+            icc_profile: None,
+        }))
+    );
+
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/bmp/v5-icc.bmp"),
+        Some(ImageDetails::Bmp(BmpDetails {
+            header_version: BmpHeaderVersion::V5,
+            header_size: 124,
+            bit_count: 32,
+            compression: BmpCompression::Bitfields,
+            palette_size: 0,
+            top_down: true,
+            data_offset: 138,
+            icc_profile: Some(BmpIccProfile {
+                offset: 154,
+                length: 32
+            }),
+        }))
+    );
+
+    // BI_PNG leaves the bit count at 0, there is no palette
+    let mut data = std::fs::read("images/valid/bmp/sample.bmp").unwrap();
+    data[28..30].copy_from_slice(&[0, 0]);
+    data[30..34].copy_from_slice(&[5, 0, 0, 0]);
+    match ImageDetails::from_raw_data(&data) {
+        Ok(Some(ImageDetails::Bmp(details))) => {
+            assert_eq!(details.bit_count, 0);
+            assert_eq!(details.compression, BmpCompression::Png);
+            assert_eq!(details.palette_size, 0);
+        }
+        _ => panic!("bmp details expected"),
+    }
 }
 
 #[test]
@@ -393,6 +737,122 @@ fn test_ico() {
             },],
         }
     );
+
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/ico/large-png.ico"),
+        ImageInfo {
+            format: ImageFormat::ICO,
+            ext: "ico",
+            full_ext: "ico",
+            mimetype: "image/ico",
+            size: ImageSize {
+                width: 512,
+                height: 512
+            },
+            entry_sizes: vec![
+                ImageSize {
+                    width: 32,
+                    height: 32
+                },
+                ImageSize {
+                    width: 512,
+                    height: 512
+                },
+            ],
+        }
+    );
+
+    // PNG payload of 0xFFFFFFFF x 0xFFFFFFFF
+    let mut data = b"\x00\x00\x01\x00\x01\x00".to_vec();
+    data.extend_from_slice(&[0, 0, 0, 0, 1, 0, 32, 0, 24, 0, 0, 0, 22, 0, 0, 0]);
+    data.extend_from_slice(b"\x89PNG\r\n\x1A\n\x00\x00\x00\x0DIHDR");
+    data.extend_from_slice(&[0xFF; 8]);
+    assert_eq_ok!(
+        ImageInfo::from_raw_data(&data),
+        ImageInfo {
+            format: ImageFormat::ICO,
+            ext: "ico",
+            full_ext: "ico",
+            mimetype: "image/ico",
+            size: ImageSize {
+                width: 4294967295,
+                height: 4294967295
+            },
+            entry_sizes: vec![ImageSize {
+                width: 4294967295,
+                height: 4294967295
+            }],
+        }
+    );
+
+    // equal areas, the first entry is the main image
+    let mut data = b"\x00\x00\x01\x00\x02\x00".to_vec();
+    data.extend_from_slice(&[16, 32, 0, 0, 1, 0, 32, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    data.extend_from_slice(&[32, 16, 0, 0, 1, 0, 32, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    let info = ImageInfo::from_raw_data(&data).unwrap();
+    assert_eq!(
+        info.size,
+        ImageSize {
+            width: 16,
+            height: 32
+        }
+    );
+}
+
+#[test]
+fn test_ico_details() {
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/ico/large-png.ico"),
+        Some(ImageDetails::Ico(IcoDetails {
+            entries: vec![
+                IcoEntry {
+                    size: ImageSize {
+                        width: 32,
+                        height: 32
+                    },
+                    color_count: 0,
+                    planes: Some(1),
+                    bit_count: Some(32),
+                    hotspot: None,
+                    byte_size: 4264,
+                    offset: 38,
+                    payload: IcoPayload::Dib,
+                },
+                IcoEntry {
+                    size: ImageSize {
+                        width: 512,
+                        height: 512
+                    },
+                    color_count: 0,
+                    planes: Some(1),
+                    bit_count: Some(8),
+                    hotspot: None,
+                    byte_size: 334,
+                    offset: 4302,
+                    payload: IcoPayload::Png,
+                },
+            ],
+        }))
+    );
+
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/cur/sample.cur"),
+        Some(ImageDetails::Ico(IcoDetails {
+            entries: vec![IcoEntry {
+                size: ImageSize {
+                    width: 32,
+                    height: 32
+                },
+                color_count: 0,
+                planes: None,
+                bit_count: None,
+                hotspot: Some(CursorHotspot { x: 0, y: 0 }),
+                byte_size: 4264,
+                offset: 22,
+                payload: IcoPayload::Dib,
+            }],
+        }))
+    );
 }
 
 #[test]
@@ -429,6 +889,118 @@ fn test_gif() {
             entry_sizes: vec![],
         }
     );
+
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/gif/animated.gif"),
+        ImageInfo {
+            format: ImageFormat::GIF,
+            ext: "gif",
+            full_ext: "gif",
+            mimetype: "image/gif",
+            size: ImageSize {
+                width: 8,
+                height: 6
+            },
+            entry_sizes: vec![],
+        }
+    );
+}
+
+#[test]
+fn test_gif_details() {
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/gif/animated.gif"),
+        Some(ImageDetails::Gif(GifDetails {
+            version: "89a".to_string(),
+            global_color_table_size: Some(4),
+            background_color_index: 1,
+            pixel_aspect_ratio: 49,
+            frames: vec![
+                GifFrame {
+                    left: 0,
+                    top: 0,
+                    width: 8,
+                    height: 6,
+                    interlaced: false,
+                    local_color_table_size: None,
+                    disposal_method: 1,
+                    delay: 10,
+                    transparent_index: Some(0),
+                },
+                GifFrame {
+                    left: 2,
+                    top: 1,
+                    width: 4,
+                    height: 3,
+                    interlaced: true,
+                    local_color_table_size: Some(4),
+                    disposal_method: 2,
+                    delay: 25,
+                    transparent_index: None,
+                },
+            ],
+            application_extensions: vec!["NETSCAPE2.0".to_string(), "XMP DataXMP".to_string()],
+            loop_count: Some(3),
+            xmp: Some(
+                "<?xpacket begin=\"\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?><x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/><?xpacket end=\"w\"?>".to_string()
+            ),
+            has_trailer: true,
+        }))
+    );
+
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/gif/sample.gif"),
+        Some(ImageDetails::Gif(GifDetails {
+            version: "87a".to_string(),
+            global_color_table_size: Some(256),
+            background_color_index: 255,
+            pixel_aspect_ratio: 0,
+            frames: vec![GifFrame {
+                left: 0,
+                top: 0,
+                width: 123,
+                height: 456,
+                interlaced: false,
+                local_color_table_size: None,
+                disposal_method: 0,
+                delay: 0,
+                transparent_index: None,
+            }],
+            application_extensions: vec![],
+            loop_count: None,
+            xmp: None,
+            has_trailer: true,
+        }))
+    );
+
+    // truncated inside the second frame
+    let data = std::fs::read("images/valid/gif/animated.gif").unwrap();
+    match ImageDetails::from_raw_data(&data[..data.len() - 4]) {
+        Ok(Some(ImageDetails::Gif(details))) => {
+            assert_eq!(details.frames.len(), 2);
+            assert!(!details.has_trailer);
+        }
+        _ => panic!("gif details expected"),
+    }
+
+    // image data spread over more sub-blocks than one chunk holds
+    let mut data = b"GIF89a\x02\x00\x02\x00\x00\x00\x00".to_vec();
+    for sub_blocks in [400, 1] {
+        data.extend_from_slice(b"\x2C\x00\x00\x00\x00\x02\x00\x02\x00\x00\x02");
+        for _ in 0..sub_blocks {
+            data.push(255);
+            data.extend_from_slice(&[0; 255]);
+        }
+        data.push(0);
+    }
+    data.push(0x3B);
+    match ImageDetails::from_raw_data(&data) {
+        Ok(Some(ImageDetails::Gif(details))) => {
+            assert_eq!(details.frames.len(), 2);
+            assert!(details.has_trailer);
+        }
+        _ => panic!("gif details expected"),
+    }
 }
 
 #[test]
@@ -461,6 +1033,21 @@ fn test_hdr() {
             entry_sizes: vec![],
         }
     );
+    // X first, scanlines run top to bottom along the columns
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/hdr/flipped.hdr"),
+        ImageInfo {
+            format: ImageFormat::HDR,
+            ext: "hdr",
+            full_ext: "hdr",
+            mimetype: "image/vnd.radiance",
+            size: ImageSize {
+                width: 3,
+                height: 2
+            },
+            entry_sizes: vec![],
+        }
+    );
 }
 
 #[test]
@@ -512,34 +1099,192 @@ fn test_icns() {
             ],
         }
     );
-}
 
-#[test]
-fn test_jpeg2000_code_stream() {
     assert_eq_ok!(
-        ImageInfo::from_file_path("images/valid/j2k/balloon.j2k"),
+        ImageInfo::from_file_path("images/valid/icns/retina.icns"),
         ImageInfo {
-            format: ImageFormat::J2K,
-            ext: "j2k",
-            full_ext: "j2k",
-            mimetype: "image/j2k",
+            format: ImageFormat::ICNS,
+            ext: "icns",
+            full_ext: "icns",
+            mimetype: "image/icns",
             size: ImageSize {
-                width: 2717,
-                height: 3701
+                width: 512,
+                height: 512
             },
-            entry_sizes: vec![],
+            entry_sizes: vec![
+                ImageSize {
+                    width: 32,
+                    height: 32
+                },
+                ImageSize {
+                    width: 512,
+                    height: 512
+                },
+                ImageSize {
+                    width: 16,
+                    height: 16
+                },
+            ],
         }
     );
 
+    // embedded PNGs of 0xFFFFFFFF x 0xFFFFFFFF
     assert_eq_ok!(
-        ImageInfo::from_file_path("images/valid/j2k/sample.j2k"),
+        ImageInfo::from_file_path("images/invalid/crash_icns_3"),
         ImageInfo {
-            format: ImageFormat::J2K,
-            ext: "j2k",
-            full_ext: "j2k",
-            mimetype: "image/j2k",
-            size: ImageSize {
-                width: 123,
+            format: ImageFormat::ICNS,
+            ext: "icns",
+            full_ext: "icns",
+            mimetype: "image/icns",
+            size: ImageSize {
+                width: 4294967295,
+                height: 4294967295
+            },
+            entry_sizes: vec![
+                ImageSize {
+                    width: 4294967295,
+                    height: 4294967295
+                },
+                ImageSize {
+                    width: 4294967295,
+                    height: 4294967295
+                },
+                ImageSize {
+                    width: 16,
+                    height: 16
+                },
+            ],
+        }
+    );
+}
+
+#[test]
+fn test_icns_details() {
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/icns/retina.icns"),
+        Some(ImageDetails::Icns(IcnsDetails {
+            entries: vec![
+                IcnsEntry {
+                    os_type: "TOC ".to_string(),
+                    offset: 16,
+                    length: 24,
+                    scale: 1,
+                    payload: IcnsPayload::Other,
+                    size: None
+                },
+                IcnsEntry {
+                    os_type: "icnV".to_string(),
+                    offset: 48,
+                    length: 4,
+                    scale: 1,
+                    payload: IcnsPayload::Other,
+                    size: None
+                },
+                IcnsEntry {
+                    os_type: "ic11".to_string(),
+                    offset: 60,
+                    length: 75,
+                    scale: 2,
+                    payload: IcnsPayload::Png,
+                    size: Some(ImageSize {
+                        width: 32,
+                        height: 32
+                    })
+                },
+                IcnsEntry {
+                    os_type: "ic14".to_string(),
+                    offset: 143,
+                    length: 334,
+                    scale: 2,
+                    payload: IcnsPayload::Png,
+                    size: Some(ImageSize {
+                        width: 512,
+                        height: 512
+                    })
+                },
+                IcnsEntry {
+                    os_type: "ic04".to_string(),
+                    offset: 485,
+                    length: 12,
+                    scale: 1,
+                    payload: IcnsPayload::Argb,
+                    size: Some(ImageSize {
+                        width: 16,
+                        height: 16
+                    })
+                },
+                IcnsEntry {
+                    os_type: "info".to_string(),
+                    offset: 505,
+                    length: 29,
+                    scale: 1,
+                    payload: IcnsPayload::Other,
+                    size: None
+                }
+            ],
+        }))
+    );
+
+    let icns_entries = |data: &[u8]| match ImageDetails::from_raw_data(data) {
+        Ok(Some(ImageDetails::Icns(details))) => details.entries,
+        _ => panic!("icns details expected"),
+    };
+
+    // an unknown type is skipped rather than failing the whole file
+    let data = std::fs::read("images/invalid/crash_icns_2").unwrap();
+    let entries = icns_entries(&data);
+    assert_eq!(entries[0].os_type, "i\\32");
+    assert_eq!(entries[0].payload, IcnsPayload::Other);
+    assert_eq!(entries[0].size, None);
+    assert_eq!(
+        ImageInfo::from_raw_data(&data).unwrap().size,
+        ImageSize {
+            width: 128,
+            height: 128
+        }
+    );
+
+    // a JPEG is not taken for JPEG 2000
+    let jpeg = std::fs::read("images/valid/jpg/lossless.jpg").unwrap();
+    let mut data = b"icns".to_vec();
+    data.extend_from_slice(&(8 + 8 + jpeg.len() as u32 + 12).to_be_bytes());
+    data.extend_from_slice(b"ic07");
+    data.extend_from_slice(&(8 + jpeg.len() as u32).to_be_bytes());
+    data.extend_from_slice(&jpeg);
+    data.extend_from_slice(b"zzzz\x00\x00\x00\x0C\x00\x00\x00\x00");
+    let entries = icns_entries(&data);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].payload, IcnsPayload::Other);
+    assert_eq!(entries[1].payload, IcnsPayload::Other);
+    assert_eq!(entries[1].size, None);
+}
+
+#[test]
+fn test_jpeg2000_code_stream() {
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/j2k/balloon.j2k"),
+        ImageInfo {
+            format: ImageFormat::J2K,
+            ext: "j2k",
+            full_ext: "j2k",
+            mimetype: "image/j2k",
+            size: ImageSize {
+                width: 2717,
+                height: 3701
+            },
+            entry_sizes: vec![],
+        }
+    );
+
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/j2k/sample.j2k"),
+        ImageInfo {
+            format: ImageFormat::J2K,
+            ext: "j2k",
+            full_ext: "j2k",
+            mimetype: "image/j2k",
+            size: ImageSize {
+                width: 123,
                 height: 456
             },
             entry_sizes: vec![],
@@ -575,6 +1320,22 @@ fn test_jpeg2000_code_stream() {
             entry_sizes: vec![],
         }
     );
+
+    // Xsiz and Ysiz, the image offset (3, 5) is left to the details
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/j2k/offset.j2k"),
+        ImageInfo {
+            format: ImageFormat::J2K,
+            ext: "j2k",
+            full_ext: "j2k",
+            mimetype: "image/j2k",
+            size: ImageSize {
+                width: 100,
+                height: 80
+            },
+            entry_sizes: vec![],
+        }
+    );
 }
 
 #[test]
@@ -644,6 +1405,132 @@ fn test_jpx() {
             entry_sizes: vec![],
         }
     );
+
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/jpx/multiple.jpx"),
+        ImageInfo {
+            format: ImageFormat::JPX,
+            ext: "jpx",
+            full_ext: "jpx",
+            mimetype: "image/jpx",
+            size: ImageSize {
+                width: 64,
+                height: 48
+            },
+            entry_sizes: vec![
+                ImageSize {
+                    width: 64,
+                    height: 48
+                },
+                ImageSize {
+                    width: 16,
+                    height: 16
+                },
+                ImageSize {
+                    width: 32,
+                    height: 24
+                }
+            ],
+        }
+    );
+}
+
+#[test]
+fn test_jpeg2000_details() {
+    let size = |width, height| ImageSize { width, height };
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/j2k/offset.j2k"),
+        Some(ImageDetails::Jpeg2000(Jpeg2000Details {
+            capabilities: 0,
+            is_htj2k: false,
+            size: size(97, 75),
+            image_offset: (3, 5),
+            tile_size: size(32, 32),
+            tile_offset: (0, 0),
+            tile_columns: 4,
+            tile_rows: 3,
+            components: vec![
+                Jpeg2000Component {
+                    bit_depth: 8,
+                    signed: false,
+                    horizontal_separation: 1,
+                    vertical_separation: 1,
+                },
+                Jpeg2000Component {
+                    bit_depth: 12,
+                    signed: true,
+                    horizontal_separation: 2,
+                    vertical_separation: 2,
+                },
+            ],
+            decomposition_levels: 3,
+            reversible: true,
+            layers: 3,
+            progression_order: 2,
+            resolutions: vec![size(97, 75), size(48, 37), size(24, 18), size(12, 9)],
+            reader_requirements: None,
+        }))
+    );
+
+    let jpeg2000_details = |path| match ImageDetails::from_file_path(path) {
+        Ok(Some(ImageDetails::Jpeg2000(details))) => details,
+        _ => panic!("jpeg 2000 details expected"),
+    };
+
+    let details = jpeg2000_details("images/valid/jp2/sample.jp2");
+    assert_eq!(details.components.len(), 3);
+    assert_eq!(
+        details.resolutions,
+        vec![
+            size(123, 456),
+            size(62, 228),
+            size(31, 114),
+            size(16, 57),
+            size(8, 29),
+            size(4, 15)
+        ]
+    );
+
+    let details = jpeg2000_details("images/valid/jp2/jpx_disguised_as_jp2.jp2");
+    assert_eq!(details.tile_size, size(1024, 1024));
+    assert_eq!((details.tile_columns, details.tile_rows), (3, 4));
+    assert!(!details.reversible);
+
+    let details = jpeg2000_details("images/valid/jph/byte.jph");
+    assert!(details.is_htj2k);
+    assert_eq!(details.capabilities, 0x4000);
+
+    let details = jpeg2000_details("images/valid/jpx/sample.jpx");
+    assert!(!details.is_htj2k);
+    assert_eq!(details.size, size(2717, 3701));
+
+    let details = jpeg2000_details("images/valid/jpx/multiple.jpx");
+    assert_eq!(details.size, size(64, 48));
+    assert_eq!(
+        details.reader_requirements,
+        Some(Jpeg2000ReaderRequirements {
+            fully_understand_mask: 0x80,
+            decode_completely_mask: 0x40,
+            standard_features: vec![
+                Jpeg2000StandardFeature { id: 2, mask: 0x80 },
+                Jpeg2000StandardFeature { id: 18, mask: 0x40 },
+            ],
+            vendor_features: vec!["000102030405060708090a0b0c0d0e0f".to_string()],
+        })
+    );
+
+    // an rreq box longer than its fields
+    let data = std::fs::read("images/valid/jpx/multiple.jpx").unwrap();
+    let mut padded = data[..36].to_vec();
+    padded.extend_from_slice(&(0x26u32 + 4).to_be_bytes());
+    padded.extend_from_slice(&data[40..74]);
+    padded.extend_from_slice(&[0; 4]);
+    padded.extend_from_slice(&data[74..]);
+    assert_eq!(
+        ImageInfo::from_raw_data(&padded).unwrap().format,
+        ImageFormat::JPX
+    );
+    assert_unrecognized_err!(ImageDetails::from_raw_data(&padded));
 }
 
 #[test]
@@ -797,6 +1684,227 @@ fn test_jpg() {
             entry_sizes: vec![],
         }
     );
+
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/jpg/arithmetic.jpg"),
+        ImageInfo {
+            format: ImageFormat::JPEG,
+            ext: "jpg",
+            full_ext: "jpeg",
+            mimetype: "image/jpeg",
+            size: ImageSize {
+                width: 64,
+                height: 48
+            },
+            entry_sizes: vec![],
+        }
+    );
+
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/jpg/lossless.jpg"),
+        ImageInfo {
+            format: ImageFormat::JPEG,
+            ext: "jpg",
+            full_ext: "jpeg",
+            mimetype: "image/jpeg",
+            size: ImageSize {
+                width: 64,
+                height: 32
+            },
+            entry_sizes: vec![],
+        }
+    );
+}
+
+#[test]
+fn test_jpeg_details() {
+    let jpeg_details = |path| match ImageDetails::from_file_path(path) {
+        Ok(Some(ImageDetails::Jpeg(details))) => details,
+        _ => panic!("jpeg details expected"),
+    };
+
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/jpg/arithmetic.jpg"),
+        Some(ImageDetails::Jpeg(JpegDetails {
+            sof_marker: 0xC9,
+            coding_process: JpegCodingProcess::ExtendedSequential,
+            arithmetic_coding: true,
+            differential: false,
+            precision: 8,
+            components: vec![
+                JpegComponent {
+                    id: 1,
+                    horizontal_sampling: 2,
+                    vertical_sampling: 1,
+                    quantization_table: 0
+                },
+                JpegComponent {
+                    id: 2,
+                    horizontal_sampling: 1,
+                    vertical_sampling: 1,
+                    quantization_table: 0
+                },
+                JpegComponent {
+                    id: 3,
+                    horizontal_sampling: 1,
+                    vertical_sampling: 1,
+                    quantization_table: 0
+                }
+            ],
+            restart_interval: Some(4),
+            jfif_version: None,
+            adobe_transform: Some(1),
+            quantization_tables: vec![JpegQuantizationTable {
+                id: 0,
+                precision: 0,
+                values: vec![
+                    1, 2, 6, 7, 15, 16, 28, 29, 3, 5, 8, 14, 17, 27, 30, 43, 4, 9, 13, 18, 26, 31,
+                    42, 44, 10, 12, 19, 25, 32, 41, 45, 54, 11, 20, 24, 33, 40, 46, 53, 55, 21, 23,
+                    34, 39, 47, 52, 56, 61, 22, 35, 38, 48, 51, 57, 60, 62, 36, 37, 49, 50, 58, 59,
+                    63, 64
+                ],
+            }],
+            estimated_quality: Some(73),
+        }))
+    );
+
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/jpg/lossless.jpg"),
+        Some(ImageDetails::Jpeg(JpegDetails {
+            sof_marker: 0xC3,
+            coding_process: JpegCodingProcess::Lossless,
+            arithmetic_coding: false,
+            differential: false,
+            precision: 16,
+            components: vec![JpegComponent {
+                id: 1,
+                horizontal_sampling: 1,
+                vertical_sampling: 1,
+                quantization_table: 0
+            }],
+            restart_interval: None,
+            jfif_version: Some("1.02".to_string()),
+            adobe_transform: None,
+            quantization_tables: vec![],
+            estimated_quality: None,
+        }))
+    );
+
+    let mut details = jpeg_details("images/valid/jpg/rotation-90.jpg");
+    assert_eq!(details.estimated_quality, Some(94));
+    assert_eq!(details.quantization_tables.len(), 2);
+    details.quantization_tables.clear();
+    assert_eq!(
+        details,
+        JpegDetails {
+            sof_marker: 0xC0,
+            coding_process: JpegCodingProcess::Baseline,
+            arithmetic_coding: false,
+            differential: false,
+            precision: 8,
+            components: vec![
+                JpegComponent {
+                    id: 1,
+                    horizontal_sampling: 2,
+                    vertical_sampling: 2,
+                    quantization_table: 0
+                },
+                JpegComponent {
+                    id: 2,
+                    horizontal_sampling: 1,
+                    vertical_sampling: 1,
+                    quantization_table: 1
+                },
+                JpegComponent {
+                    id: 3,
+                    horizontal_sampling: 1,
+                    vertical_sampling: 1,
+                    quantization_table: 1
+                }
+            ],
+            restart_interval: Some(252),
+            jfif_version: Some("1.01".to_string()),
+            adobe_transform: None,
+            quantization_tables: vec![],
+            estimated_quality: Some(94),
+        }
+    );
+
+    let mut details = jpeg_details("images/valid/jpg/sampleExported.jpg");
+    assert_eq!(details.estimated_quality, Some(94));
+    assert_eq!(details.quantization_tables.len(), 2);
+    details.quantization_tables.clear();
+    assert_eq!(
+        details,
+        JpegDetails {
+            sof_marker: 0xC2,
+            coding_process: JpegCodingProcess::Progressive,
+            arithmetic_coding: false,
+            differential: false,
+            precision: 8,
+            components: vec![
+                JpegComponent {
+                    id: 1,
+                    horizontal_sampling: 1,
+                    vertical_sampling: 1,
+                    quantization_table: 0
+                },
+                JpegComponent {
+                    id: 2,
+                    horizontal_sampling: 1,
+                    vertical_sampling: 1,
+                    quantization_table: 1
+                },
+                JpegComponent {
+                    id: 3,
+                    horizontal_sampling: 1,
+                    vertical_sampling: 1,
+                    quantization_table: 1
+                }
+            ],
+            restart_interval: None,
+            jfif_version: None,
+            adobe_transform: Some(1),
+            quantization_tables: vec![],
+            estimated_quality: Some(94),
+        }
+    );
+
+    let subsampling = |path| jpeg_details(path).subsampling();
+    assert_eq!(
+        subsampling("images/valid/jpg/arithmetic.jpg"),
+        Some("4:2:2")
+    );
+    assert_eq!(subsampling("images/valid/jpg/lossless.jpg"), Some("4:0:0"));
+    assert_eq!(
+        subsampling("images/valid/jpg/rotation-90.jpg"),
+        Some("4:2:0")
+    );
+    assert_eq!(subsampling("images/valid/jpg/sample.jpg"), Some("4:4:4"));
+
+    let estimated_quality = |path| jpeg_details(path).estimated_quality;
+    // the IJG luminance table scaled for quality 75
+    assert_eq!(
+        jpeg_details("images/valid/jpg/quality-75.jpg").quantization_tables,
+        vec![JpegQuantizationTable {
+            id: 0,
+            precision: 0,
+            values: vec![
+                8, 6, 5, 8, 12, 20, 26, 31, 6, 6, 7, 10, 13, 29, 30, 28, 7, 7, 8, 12, 20, 29, 35,
+                28, 7, 9, 11, 15, 26, 44, 40, 31, 9, 11, 19, 28, 34, 55, 52, 39, 12, 18, 28, 32,
+                41, 52, 57, 46, 25, 32, 39, 44, 52, 61, 60, 51, 36, 46, 48, 49, 56, 50, 52, 50
+            ],
+        }]
+    );
+    assert_eq!(
+        estimated_quality("images/valid/jpg/quality-75.jpg"),
+        Some(75)
+    );
+    assert_eq!(estimated_quality("images/valid/jpg/sample.jpg"), Some(90));
+    assert_eq!(
+        estimated_quality("images/valid/jpg/very-large.jpg"),
+        Some(10)
+    );
 }
 
 #[test]
@@ -819,6 +1927,21 @@ fn test_ktx() {
 
 #[test]
 fn test_png() {
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/png/ancillary.png"),
+        ImageInfo {
+            format: ImageFormat::PNG,
+            ext: "png",
+            full_ext: "png",
+            mimetype: "image/png",
+            size: ImageSize {
+                width: 1,
+                height: 1
+            },
+            entry_sizes: vec![],
+        }
+    );
+
     assert_eq_ok!(
         ImageInfo::from_file_path("images/valid/png/sample.png"),
         ImageInfo {
@@ -865,6 +1988,97 @@ fn test_png() {
     );
 }
 
+#[test]
+fn test_png_details() {
+    let chunk = |chunk_type: &str, offset, length| PngChunk {
+        chunk_type: chunk_type.to_string(),
+        offset,
+        length,
+    };
+    let text = |chunk_type: &str, keyword: &str| PngText {
+        chunk_type: chunk_type.to_string(),
+        keyword: keyword.to_string(),
+    };
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/png/ancillary.png"),
+        Some(ImageDetails::Png(PngDetails {
+            bit_depth: 8,
+            color_type: 2,
+            interlace_method: 1,
+            chunks: vec![
+                chunk("IHDR", 8, 13),
+                chunk("gAMA", 33, 4),
+                chunk("cHRM", 49, 32),
+                chunk("sBIT", 93, 3),
+                chunk("tEXt", 108, 12),
+                chunk("zTXt", 132, 35),
+                chunk("iTXt", 179, 34),
+                chunk("tIME", 225, 7),
+                chunk("tRNS", 244, 6),
+                chunk("IDAT", 262, 12),
+                chunk("IEND", 286, 0),
+            ],
+            gamma: Some(0.45455),
+            chromaticities: Some(PngChromaticities {
+                white_point: (0.3127, 0.329),
+                red: (0.64, 0.33),
+                green: (0.3, 0.6),
+                blue: (0.15, 0.06),
+            }),
+            significant_bits: Some(vec![5, 6, 5]),
+            texts: vec![
+                text("tEXt", "Title"),
+                text("zTXt", "Comment"),
+                text("iTXt", "XML:com.adobe.xmp"),
+            ],
+            last_modified: Some(PngTime {
+                year: 2024,
+                month: 5,
+                day: 6,
+                hour: 7,
+                minute: 8,
+                second: 9,
+            }),
+            has_transparency: true,
+            has_iend: true,
+        }))
+    );
+
+    let png_details = |details| match details {
+        Ok(Some(ImageDetails::Png(details))) => details,
+        _ => panic!("png details expected"),
+    };
+
+    let details = png_details(ImageDetails::from_file_path(
+        "images/valid/png/sample_fried.png",
+    ));
+    assert_eq!(details.chunks[0], chunk("CgBI", 8, 4));
+    assert_eq!(details.chunks.len(), 7);
+    assert!(details.has_iend);
+
+    // truncated upload, the IEND chunk and part of IDAT are missing
+    let data = std::fs::read("images/valid/png/sample_fried.png").unwrap();
+    let details = png_details(ImageDetails::from_raw_data(&data[..3000]));
+    assert_eq!(details.chunks.len(), 5);
+    assert_eq!(details.chunks[4], chunk("pHYs", 2738, 9));
+    assert!(!details.has_iend);
+
+    // sBIT and tIME longer than the spec allows are listed but not decoded
+    let data = std::fs::read("images/valid/png/sample.png").unwrap();
+    let mut corrupt = data[..33].to_vec();
+    corrupt.extend_from_slice(b"\x00\x00\x00\x40sBIT");
+    corrupt.extend_from_slice(&[8; 0x40 + 4]);
+    corrupt.extend_from_slice(b"\x00\x00\x00\x08tIME");
+    corrupt.extend_from_slice(&[1; 8 + 4]);
+    corrupt.extend_from_slice(&data[33..]);
+    let details = png_details(ImageDetails::from_raw_data(&corrupt));
+    assert_eq!(details.chunks[1], chunk("sBIT", 33, 64));
+    assert_eq!(details.chunks[2], chunk("tIME", 109, 8));
+    assert_eq!(details.significant_bits, None);
+    assert_eq!(details.last_modified, None);
+    assert!(details.has_iend);
+}
+
 #[test]
 fn test_psd() {
     assert_eq_ok!(
@@ -881,6 +2095,67 @@ fn test_psd() {
             entry_sizes: vec![],
         }
     );
+
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/psd/large.psb"),
+        ImageInfo {
+            format: ImageFormat::PSD,
+            ext: "psb",
+            full_ext: "psb",
+            mimetype: "image/psd",
+            size: ImageSize {
+                width: 40,
+                height: 30
+            },
+            entry_sizes: vec![],
+        }
+    );
+}
+
+#[test]
+fn test_psd_details() {
+    let resource = |id, name: &str, offset, length| PsdImageResource {
+        id,
+        name: name.to_string(),
+        offset,
+        length,
+    };
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/psd/thumbnail.psd"),
+        Some(ImageDetails::Psd(PsdDetails {
+            version: 1,
+            channels: 3,
+            depth: 8,
+            color_mode: PsdColorMode::Rgb,
+            layer_count: Some(1),
+            image_resources: vec![
+                resource(1005, "", 46, 16),
+                resource(1024, "", 74, 2),
+                resource(1036, "", 88, 761),
+            ],
+        }))
+    );
+
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/psd/large.psb"),
+        Some(ImageDetails::Psd(PsdDetails {
+            version: 2,
+            channels: 1,
+            depth: 16,
+            color_mode: PsdColorMode::Grayscale,
+            layer_count: Some(2),
+            image_resources: vec![resource(1005, "", 46, 16), resource(2000, "Path 1", 80, 26)],
+        }))
+    );
+
+    // 64-bit LMsk length running past the end of the file
+    let mut data = std::fs::read("images/valid/psd/large.psb").unwrap();
+    data[130..134].copy_from_slice(b"LMsk");
+    data[134..142].copy_from_slice(&[0xFF; 8]);
+    match ImageDetails::from_raw_data(&data) {
+        Ok(Some(ImageDetails::Psd(details))) => assert_eq!(details.layer_count, Some(0)),
+        _ => panic!("psd details expected"),
+    }
 }
 
 #[test]
@@ -899,6 +2174,60 @@ fn test_qoi() {
             entry_sizes: vec![],
         }
     );
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/qoi/linear-rgba.qoi"),
+        ImageInfo {
+            format: ImageFormat::QOI,
+            ext: "qoi",
+            full_ext: "qoi",
+            mimetype: "image/qoi",
+            size: ImageSize {
+                width: 2,
+                height: 2
+            },
+            entry_sizes: vec![],
+        }
+    );
+
+    // zero width
+    let mut data = std::fs::read("images/valid/qoi/sample.qoi").unwrap();
+    data[4..8].copy_from_slice(&[0, 0, 0, 0]);
+    assert_unrecognized_err!(ImageInfo::from_raw_data(&data));
+    // unknown colorspace
+    let mut data = std::fs::read("images/valid/qoi/sample.qoi").unwrap();
+    data[13] = 2;
+    assert_unrecognized_err!(ImageInfo::from_raw_data(&data));
+}
+
+#[test]
+fn test_qoi_details() {
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/qoi/sample.qoi"),
+        Some(ImageDetails::Qoi(QoiDetails {
+            channels: 3,
+            colorspace: QoiColorspace::SrgbLinearAlpha,
+            has_end_marker: true,
+        }))
+    );
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/qoi/linear-rgba.qoi"),
+        Some(ImageDetails::Qoi(QoiDetails {
+            channels: 4,
+            colorspace: QoiColorspace::Linear,
+            has_end_marker: true,
+        }))
+    );
+
+    // truncated
+    let data = std::fs::read("images/valid/qoi/sample.qoi").unwrap();
+    assert_eq_ok!(
+        ImageDetails::from_raw_data(&data[..1000]),
+        Some(ImageDetails::Qoi(QoiDetails {
+            channels: 3,
+            colorspace: QoiColorspace::SrgbLinearAlpha,
+            has_end_marker: false,
+        }))
+    );
 }
 
 #[test]
@@ -992,15 +2321,137 @@ fn test_tiff() {
             entry_sizes: vec![],
         }
     );
-}
 
-#[test]
-fn test_webp() {
     assert_eq_ok!(
-        ImageInfo::from_file_path("images/valid/webp/lossless.webp"),
+        ImageInfo::from_file_path("images/valid/tiff/multi-page.tiff"),
         ImageInfo {
-            format: ImageFormat::WEBP,
-            ext: "webp",
+            format: ImageFormat::TIFF,
+            ext: "tif",
+            full_ext: "tiff",
+            mimetype: "image/tiff",
+            size: ImageSize {
+                width: 123,
+                height: 456
+            },
+            entry_sizes: vec![
+                ImageSize {
+                    width: 123,
+                    height: 456
+                },
+                ImageSize {
+                    width: 62,
+                    height: 228
+                },
+                ImageSize {
+                    width: 30,
+                    height: 114
+                },
+            ],
+        }
+    );
+
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/tiff/BigTIFF-multi-page.tif"),
+        ImageInfo {
+            format: ImageFormat::TIFF,
+            ext: "tif",
+            full_ext: "tiff",
+            mimetype: "image/tiff",
+            size: ImageSize {
+                width: 64,
+                height: 64
+            },
+            entry_sizes: vec![
+                ImageSize {
+                    width: 64,
+                    height: 64
+                },
+                ImageSize {
+                    width: 32,
+                    height: 16
+                },
+            ],
+        }
+    );
+}
+
+#[test]
+fn test_tiff_details() {
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/tiff/multi-page.tiff"),
+        Some(ImageDetails::Tiff(TiffDetails {
+            pages: vec![
+                TiffPage {
+                    size: ImageSize {
+                        width: 123,
+                        height: 456
+                    },
+                    subfile_type: 0,
+                },
+                TiffPage {
+                    size: ImageSize {
+                        width: 62,
+                        height: 228
+                    },
+                    subfile_type: 1,
+                },
+                TiffPage {
+                    size: ImageSize {
+                        width: 30,
+                        height: 114
+                    },
+                    subfile_type: 2,
+                },
+            ],
+        }))
+    );
+
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/tiff/BigTIFF-multi-page.tif"),
+        Some(ImageDetails::Tiff(TiffDetails {
+            pages: vec![
+                TiffPage {
+                    size: ImageSize {
+                        width: 64,
+                        height: 64
+                    },
+                    subfile_type: 0,
+                },
+                TiffPage {
+                    size: ImageSize {
+                        width: 32,
+                        height: 16
+                    },
+                    subfile_type: 2,
+                },
+            ],
+        }))
+    );
+
+    if let Ok(Some(ImageDetails::Tiff(details))) =
+        ImageDetails::from_file_path("images/valid/tiff/multi-page.tiff")
+    {
+        assert_eq!(details.page_count(), 3);
+        assert!(!details.pages[0].is_reduced_resolution());
+        assert!(details.pages[1].is_reduced_resolution());
+        assert!(details.pages[2].is_page());
+    } else {
+        panic!("tiff details expected");
+    }
+
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/dds/sample.dds"),
+        None
+    );
+}
+
+#[test]
+fn test_webp() {
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/webp/lossless.webp"),
+        ImageInfo {
+            format: ImageFormat::WEBP,
+            ext: "webp",
             full_ext: "webp",
             mimetype: "image/webp",
             size: ImageSize {
@@ -1026,6 +2477,21 @@ fn test_webp() {
         }
     );
 
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/webp/animated.webp"),
+        ImageInfo {
+            format: ImageFormat::WEBP,
+            ext: "webp",
+            full_ext: "webp",
+            mimetype: "image/webp",
+            size: ImageSize {
+                width: 48,
+                height: 32
+            },
+            entry_sizes: vec![],
+        }
+    );
+
     assert_eq_ok!(
         ImageInfo::from_file_path("images/valid/webp/lossy.webp"),
         ImageInfo {
@@ -1042,6 +2508,84 @@ fn test_webp() {
     );
 }
 
+#[test]
+fn test_webp_details() {
+    let chunk = |chunk_type: &str, offset, length| WebpChunk {
+        chunk_type: chunk_type.to_string(),
+        offset,
+        length,
+    };
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/webp/animated.webp"),
+        Some(ImageDetails::Webp(WebpDetails {
+            riff_size: 184,
+            truncated: false,
+            compression: Some(WebpCompression::Lossy),
+            vp8x_flags: Some(0x16),
+            has_alpha: true,
+            has_icc: false,
+            has_exif: false,
+            has_xmp: true,
+            is_animated: true,
+            chunks: vec![
+                chunk("VP8X", 12, 10),
+                chunk("ANIM", 30, 6),
+                chunk("ANMF", 44, 56),
+                chunk("ANMF", 108, 56),
+                chunk("XMP ", 172, 12),
+            ],
+        }))
+    );
+
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/webp/exif.webp"),
+        Some(ImageDetails::Webp(WebpDetails {
+            riff_size: 112356,
+            truncated: false,
+            compression: Some(WebpCompression::Lossless),
+            vp8x_flags: Some(0x28),
+            has_alpha: false,
+            has_icc: true,
+            has_exif: true,
+            has_xmp: false,
+            is_animated: false,
+            chunks: vec![
+                chunk("VP8X", 12, 10),
+                chunk("ICCP", 30, 552),
+                chunk("VP8L", 590, 111392),
+                chunk("EXIF", 111990, 366),
+            ],
+        }))
+    );
+
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/webp/lossy.webp"),
+        Some(ImageDetails::Webp(WebpDetails {
+            riff_size: 17570,
+            truncated: false,
+            compression: Some(WebpCompression::Lossy),
+            vp8x_flags: None,
+            has_alpha: false,
+            has_icc: false,
+            has_exif: false,
+            has_xmp: false,
+            is_animated: false,
+            chunks: vec![chunk("VP8 ", 12, 17558)],
+        }))
+    );
+
+    // cut inside the EXIF chunk
+    let data = std::fs::read("images/valid/webp/exif.webp").unwrap();
+    match ImageDetails::from_raw_data(&data[..112000]) {
+        Ok(Some(ImageDetails::Webp(details))) => {
+            assert!(details.truncated);
+            assert_eq!(details.chunks.len(), 3);
+            assert!(!details.has_exif);
+        }
+        _ => panic!("webp details expected"),
+    }
+}
+
 #[test]
 fn test_tga() {
     assert_eq_ok!(
@@ -1058,6 +2602,95 @@ fn test_tga() {
             entry_sizes: vec![],
         }
     );
+
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/tga/extension.tga"),
+        ImageInfo {
+            format: ImageFormat::TGA,
+            ext: "tga",
+            full_ext: "tga",
+            mimetype: "image/tga",
+            size: ImageSize {
+                width: 4,
+                height: 2
+            },
+            entry_sizes: vec![],
+        }
+    );
+
+    assert_eq_ok!(
+        ImageInfo::from_file_path("images/valid/tga/colormap.tga"),
+        ImageInfo {
+            format: ImageFormat::TGA,
+            ext: "tga",
+            full_ext: "tga",
+            mimetype: "image/tga",
+            size: ImageSize {
+                width: 3,
+                height: 2
+            },
+            entry_sizes: vec![],
+        }
+    );
+
+    // true-color with an unused color map
+    let mut data =
+        b"\x00\x01\x02\x00\x00\x02\x00\x18\x00\x00\x00\x00\x02\x00\x02\x00\x18\x00".to_vec();
+    data.extend_from_slice(&[0; 6 + 12]);
+    let info = ImageInfo::from_raw_data(&data).unwrap();
+    assert_eq!(info.format, ImageFormat::TGA);
+    assert_eq!(
+        info.size,
+        ImageSize {
+            width: 2,
+            height: 2
+        }
+    );
+
+    // the footer doesn't excuse an invalid header
+    let mut data = vec![0; 18 + 8];
+    data.extend_from_slice(b"TRUEVISION-XFILE.\x00");
+    assert_unrecognized_err!(ImageInfo::from_raw_data(&data));
+}
+
+#[test]
+fn test_tga_details() {
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/tga/extension.tga"),
+        Some(ImageDetails::Tga(TgaDetails {
+            image_type: 10,
+            image_id: Some("imageinfo".to_string()),
+            color_map: None,
+            pixel_depth: 32,
+            alpha_bits: 8,
+            origin: TgaOrigin::TopLeft,
+            extension: Some(TgaExtension {
+                author_name: "Jane Doe".to_string(),
+                software_id: "imageinfo".to_string(),
+                software_version: Some("1.50b".to_string()),
+                pixel_aspect_ratio: Some((1, 1)),
+                gamma: Some(2.2),
+                attributes_type: 3,
+            }),
+        }))
+    );
+
+    assert_eq_ok!(
+        ImageDetails::from_file_path("images/valid/tga/colormap.tga"),
+        Some(ImageDetails::Tga(TgaDetails {
+            image_type: 1,
+            image_id: None,
+            color_map: Some(TgaColorMap {
+                first_entry: 0,
+                length: 16,
+                entry_size: 24,
+            }),
+            pixel_depth: 8,
+            alpha_bits: 0,
+            origin: TgaOrigin::BottomLeft,
+            extension: None,
+        }))
+    );
 }
 
 #[test]
@@ -1068,6 +2701,617 @@ fn test_io_error() {
     );
 }
 
+#[test]
+fn test_image_format() {
+    assert_eq!(ImageFormat::iter().count(), 21);
+    for format in ImageFormat::iter() {
+        assert_eq!(format.to_string().parse::<ImageFormat>().unwrap(), format);
+        assert_eq!(ImageFormat::from_mime(format.mime_type()), Some(format));
+        for ext in format.extensions() {
+            assert_eq!(ImageFormat::from_extension(ext), Some(format));
+        }
+    }
+
+    assert_eq!(ImageFormat::JPEG.name(), "JPEG");
+    assert_eq!(ImageFormat::JPEG.to_string(), "JPEG");
+    assert_eq!(
+        ImageFormat::JPEG.extensions(),
+        &["jpg", "jpeg", "jpe", "jfif", "jif"]
+    );
+    assert_eq!(ImageFormat::TIFF.extensions(), &["tif", "tiff"]);
+    assert_eq!(ImageFormat::ICO.mime_type(), "image/ico");
+    assert_eq!(
+        ImageFormat::ICO.mime_types(),
+        &["image/ico", "image/vnd.microsoft.icon", "image/x-icon"]
+    );
+    assert_eq!(format!("{:>5}", ImageFormat::PNG), "  PNG");
+
+    assert_eq!(ImageFormat::from_extension("JFIF"), Some(ImageFormat::JPEG));
+    assert_eq!(ImageFormat::from_extension(".tif"), Some(ImageFormat::TIFF));
+    assert_eq!(ImageFormat::from_extension("psb"), Some(ImageFormat::PSD));
+    assert_eq!(ImageFormat::from_extension("svg"), None);
+    assert_eq!(
+        ImageFormat::from_mime("image/x-icon"),
+        Some(ImageFormat::ICO)
+    );
+    assert_eq!(
+        ImageFormat::from_mime("Image/PNG; charset=binary"),
+        Some(ImageFormat::PNG)
+    );
+    assert_eq!(ImageFormat::from_mime("text/plain"), None);
+
+    assert_eq!("webp".parse::<ImageFormat>().unwrap(), ImageFormat::WEBP);
+    assert_eq!("jpe".parse::<ImageFormat>().unwrap(), ImageFormat::JPEG);
+    assert_unrecognized_err!("svg".parse::<ImageFormat>());
+
+    // what the detectors report is what the registry has, for every format
+    let mut seen = std::collections::HashSet::new();
+    let mut dirs = vec![std::path::PathBuf::from("images/valid")];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            let info = ImageInfo::from_file_path(&path).unwrap();
+            assert_eq!(ImageFormat::from_extension(info.ext), Some(info.format));
+            assert_eq!(
+                ImageFormat::from_extension(info.full_ext),
+                Some(info.format)
+            );
+            assert_eq!(info.mimetype, info.format.mime_type());
+            assert!(info.format.extensions().contains(&info.ext));
+            assert!(info.format.extensions().contains(&info.full_ext));
+            seen.insert(info.format);
+        }
+    }
+    assert_eq!(seen.len(), ImageFormat::iter().count());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_image_info_json() {
+    let json = r#"{"format":"JPX","ext":"jpx","full_ext":"jpx","mimetype":"image/jpx","size":{"width":64,"height":48},"entry_sizes":[{"width":64,"height":48},{"width":16,"height":16},{"width":32,"height":24}]}"#;
+    let info = ImageInfo::from_file_path("images/valid/jpx/multiple.jpx").unwrap();
+    assert_eq!(serde_json::to_string(&info).unwrap(), json);
+    assert_eq!(serde_json::from_str::<ImageInfo>(json).unwrap(), info);
+
+    // formats are written as Display writes them
+    for format in ImageFormat::iter() {
+        assert_eq!(
+            serde_json::to_string(&format).unwrap(),
+            format!("\"{}\"", format)
+        );
+    }
+
+    // including the detectors' own strings
+    for path in [
+        "images/valid/ico/multi-size.ico",
+        "images/valid/jpg/sample.jpg",
+        "images/valid/psd/large.psb",
+    ] {
+        let info = ImageInfo::from_file_path(path).unwrap();
+        let json = serde_json::to_string(&info).unwrap();
+        assert_eq!(serde_json::from_str::<ImageInfo>(&json).unwrap(), info);
+    }
+
+    // aliases of the registry are accepted, unknown strings are not
+    let info: ImageInfo = serde_json::from_str(
+        r#"{"format":"JPEG","ext":"jfif","full_ext":"jpeg","mimetype":"image/pjpeg","size":{"width":1,"height":2},"entry_sizes":[]}"#,
+    )
+    .unwrap();
+    assert_eq!(info.ext, "jfif");
+    assert_eq!(info.mimetype, "image/pjpeg");
+    assert!(serde_json::from_str::<ImageInfo>(
+        r#"{"format":"JPEG","ext":"png","full_ext":"jpeg","mimetype":"image/jpeg","size":{"width":1,"height":2},"entry_sizes":[]}"#,
+    )
+    .is_err());
+    assert!(serde_json::from_str::<ImageInfo>(
+        r#"{"format":"SVG","ext":"svg","full_ext":"svg","mimetype":"image/svg+xml","size":{"width":1,"height":2},"entry_sizes":[]}"#,
+    )
+    .is_err());
+
+    assert_eq!(
+        serde_json::from_str::<ImageFormat>(r#""WEBP""#).unwrap(),
+        ImageFormat::WEBP
+    );
+}
+
+#[test]
+fn test_sniff() {
+    // agrees with the detectors on every valid image
+    let mut dirs = vec![std::path::PathBuf::from("images/valid")];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            let info = ImageInfo::from_file_path(&path).unwrap();
+            let sniff = Sniff::from_file_path(&path).unwrap();
+            if info.format == ImageFormat::TGA {
+                assert!(sniff.candidates.contains(&SniffCandidate {
+                    format: ImageFormat::TGA,
+                    confidence: SniffConfidence::Low,
+                }));
+            } else {
+                assert_eq!(sniff.format(), info.format, "{:?}", path);
+            }
+        }
+    }
+
+    assert_eq_ok!(
+        Sniff::from_file_path("images/valid/png/sample.png"),
+        Sniff {
+            candidates: vec![SniffCandidate {
+                format: ImageFormat::PNG,
+                confidence: SniffConfidence::High,
+            }],
+        }
+    );
+    assert_eq_ok!(
+        Sniff::from_file_path("images/valid/ico/sample.ico"),
+        Sniff {
+            candidates: vec![SniffCandidate {
+                format: ImageFormat::ICO,
+                confidence: SniffConfidence::Medium,
+            }],
+        }
+    );
+
+    // the magic is enough, even when the size can't be parsed
+    let data = std::fs::read("images/valid/heic/sample.heic").unwrap();
+    assert_unrecognized_err!(ImageInfo::from_raw_data(&data[..100]));
+    assert_eq!(
+        Sniff::from_raw_data(&data[..100]).unwrap().format(),
+        ImageFormat::HEIC
+    );
+
+    // JP2 signature without the file type box
+    let data = std::fs::read("images/valid/jp2/sample.jp2").unwrap();
+    assert_eq_ok!(
+        Sniff::from_raw_data(&data[..12]),
+        Sniff {
+            candidates: vec![
+                SniffCandidate {
+                    format: ImageFormat::JP2,
+                    confidence: SniffConfidence::Medium,
+                },
+                SniffCandidate {
+                    format: ImageFormat::JPH,
+                    confidence: SniffConfidence::Medium,
+                },
+                SniffCandidate {
+                    format: ImageFormat::JPX,
+                    confidence: SniffConfidence::Medium,
+                },
+            ],
+        }
+    );
+
+    // reads no more than SNIFF_LENGTH bytes, without seeking
+    let data = std::fs::read("images/valid/jpg/very-large.jpg").unwrap();
+    let mut reader = &data[..];
+    assert_eq!(
+        Sniff::from_reader(&mut reader).unwrap().format(),
+        ImageFormat::JPEG
+    );
+    assert_eq!(data.len() - reader.len(), SNIFF_LENGTH);
+
+    assert_unrecognized_err!(Sniff::from_raw_data(b"hello, world"));
+    assert_unrecognized_err!(Sniff::from_raw_data(b""));
+}
+
+#[test]
+fn test_analysis() {
+    let formats = |analysis: &Analysis| {
+        analysis
+            .matches
+            .iter()
+            .map(|info| info.format)
+            .collect::<Vec<_>>()
+    };
+    let appended = |path: &str, tail: &[u8]| {
+        let mut data = std::fs::read(path).unwrap();
+        let end = data.len() as u64;
+        data.extend_from_slice(tail);
+        (Analysis::from_raw_data(&data).unwrap(), end)
+    };
+
+    let analysis = Analysis::from_file_path("images/valid/png/sample.png").unwrap();
+    assert_eq!(formats(&analysis), vec![ImageFormat::PNG]);
+    assert_eq!(analysis.logical_end, Some(120444));
+    assert_eq!(analysis.trailing_data, None);
+    assert!(!analysis.is_polyglot());
+
+    // PNG with an appended ZIP
+    let zip = b"PK\x05\x06\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+    let (analysis, end) = appended("images/valid/png/sample.png", zip);
+    assert_eq!(formats(&analysis), vec![ImageFormat::PNG]);
+    assert_eq!(analysis.logical_end, Some(end));
+    assert_eq!(
+        analysis.trailing_data,
+        Some(TrailingData {
+            offset: end,
+            length: zip.len() as u64,
+        })
+    );
+    assert!(analysis.is_polyglot());
+
+    // a TGA footer alone doesn't make the PNG header a TGA one
+    let (analysis, end) = appended(
+        "images/valid/png/sample.png",
+        b"\x00\x00\x00\x00\x00\x00\x00\x00TRUEVISION-XFILE.\x00",
+    );
+    assert_eq!(formats(&analysis), vec![ImageFormat::PNG]);
+    assert_eq!(
+        analysis.trailing_data,
+        Some(TrailingData {
+            offset: end,
+            length: 26,
+        })
+    );
+
+    let (analysis, end) = appended("images/valid/jpg/progressive.jpg", b"<?php ?>");
+    assert_eq!(analysis.logical_end, Some(end));
+    assert_eq!(analysis.trailing_data.unwrap().length, 8);
+    let (analysis, end) = appended("images/valid/gif/animated.gif", b"=1;alert(1);");
+    assert_eq!(analysis.logical_end, Some(end));
+    assert_eq!(analysis.trailing_data.unwrap().length, 12);
+    let (analysis, end) = appended("images/valid/webp/lossy.webp", b"junk");
+    assert_eq!(analysis.logical_end, Some(end));
+    assert_eq!(analysis.trailing_data.unwrap().length, 4);
+
+    // the gain map image follows the primary one
+    let analysis = Analysis::from_file_path("images/valid/jpg/ultra-hdr.jpg").unwrap();
+    assert_eq!(
+        analysis.trailing_data,
+        Some(TrailingData {
+            offset: 28798,
+            length: 1002,
+        })
+    );
+
+    // no end found in truncated data
+    let data = std::fs::read("images/valid/png/sample_fried.png").unwrap();
+    let analysis = Analysis::from_raw_data(&data[..3000]).unwrap();
+    assert_eq!(analysis.logical_end, None);
+    assert_eq!(analysis.trailing_data, None);
+
+    // detected from the IHDR header, too short for the details
+    let data = std::fs::read("images/valid/png/sample.png").unwrap();
+    assert_eq!(
+        ImageInfo::from_raw_data(&data[..24]).unwrap().format,
+        ImageFormat::PNG
+    );
+    let analysis = Analysis::from_raw_data(&data[..24]).unwrap();
+    assert_eq!(formats(&analysis), vec![ImageFormat::PNG]);
+    assert_eq!(analysis.logical_end, None);
+    assert_eq!(analysis.trailing_data, None);
+
+    // formats without a known end
+    let analysis = Analysis::from_file_path("images/valid/bmp/sample.bmp").unwrap();
+    assert_eq!(formats(&analysis), vec![ImageFormat::BMP]);
+    assert_eq!(analysis.logical_end, None);
+
+    assert_unrecognized_err!(Analysis::from_file_path("images/invalid/sample.png"));
+}
+
+#[test]
+fn test_extension_check() {
+    assert_eq_ok!(
+        ExtensionCheck::from_file_path("images/valid/jpg/sample.jpg"),
+        ExtensionCheck {
+            extension: Some("jpg".to_string()),
+            format: ImageFormat::JPEG,
+            matches: true,
+            suggested_extension: "jpg",
+        }
+    );
+    assert_eq_ok!(
+        ExtensionCheck::from_file_path("images/valid/psd/large.psb"),
+        ExtensionCheck {
+            extension: Some("psb".to_string()),
+            format: ImageFormat::PSD,
+            matches: true,
+            suggested_extension: "psb",
+        }
+    );
+
+    let data = std::fs::read("images/valid/jpg/sample.jpg").unwrap();
+    assert_eq_ok!(
+        ExtensionCheck::from_raw_data("photo.JPEG", &data),
+        ExtensionCheck {
+            extension: Some("JPEG".to_string()),
+            format: ImageFormat::JPEG,
+            matches: true,
+            suggested_extension: "jpg",
+        }
+    );
+    assert_eq_ok!(
+        ExtensionCheck::from_raw_data("uploads/photo.png", &data),
+        ExtensionCheck {
+            extension: Some("png".to_string()),
+            format: ImageFormat::JPEG,
+            matches: false,
+            suggested_extension: "jpg",
+        }
+    );
+    assert_eq_ok!(
+        ExtensionCheck::from_raw_data("photo", &data),
+        ExtensionCheck {
+            extension: None,
+            format: ImageFormat::JPEG,
+            matches: false,
+            suggested_extension: "jpg",
+        }
+    );
+    assert_unrecognized_err!(ExtensionCheck::from_file_path("images/invalid/sample.png"));
+
+    // check mode of the binary
+    let run = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_imageinfo"))
+            .arg("--check")
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    let output = run(&["images/valid/png/sample.png", "images/valid/gif/sample.gif"]);
+    assert_eq!(output.status.code(), Some(0));
+    let output = run(&["images/valid/png/sample.png", "images/invalid/sample.png"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "images/valid/png/sample.png: ok (PNG)\n"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "images/invalid/sample.png: error, unrecognized image format\n"
+    );
+
+    let dir = std::env::temp_dir().join("imageinfo_extension_check");
+    std::fs::create_dir_all(&dir).unwrap();
+    let renamed = dir.join("sample.png");
+    std::fs::write(&renamed, &data).unwrap();
+    let output = run(&[renamed.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .ends_with("sample.png: mismatch, content is JPEG, expected extension .jpg\n"));
+}
+
+#[test]
+fn test_decode_policy() {
+    assert_eq_ok!(
+        DecodeEstimate::from_file_path("images/valid/png/sample_apng.png"),
+        DecodeEstimate {
+            format: ImageFormat::PNG,
+            size: ImageSize {
+                width: 480,
+                height: 400
+            },
+            channels: 4,
+            bytes_per_channel: 1,
+            frames: 34,
+            pixels: 480 * 400 * 34,
+            bytes: 480 * 400 * 34 * 4,
+            largest_width: 480,
+            largest_height: 400,
+            largest_frame_pixels: 480 * 400,
+            largest_aspect_ratio: 1.2,
+        }
+    );
+    // composite and two 16-bit gray layers
+    assert_eq_ok!(
+        DecodeEstimate::from_file_path("images/valid/psd/large.psb"),
+        DecodeEstimate {
+            format: ImageFormat::PSD,
+            size: ImageSize {
+                width: 40,
+                height: 30
+            },
+            channels: 1,
+            bytes_per_channel: 2,
+            frames: 3,
+            pixels: 3600,
+            bytes: 7200,
+            largest_width: 40,
+            largest_height: 30,
+            largest_frame_pixels: 1200,
+            largest_aspect_ratio: 40.0 / 30.0,
+        }
+    );
+    // float RGB
+    let estimate = DecodeEstimate::from_file_path("images/valid/hdr/sample2.hdr").unwrap();
+    assert_eq!(estimate.bytes, 1024 * 512 * 3 * 4);
+    // 256 components of 8 bits, more channels than a u8 holds
+    let mut data = b"\xFF\x4F\xFF\x51\x03\x26\x00\x00".to_vec();
+    for value in [8u32, 8, 0, 0, 8, 8, 0, 0] {
+        data.extend_from_slice(&value.to_be_bytes());
+    }
+    data.extend_from_slice(b"\x01\x00");
+    for _ in 0..256 {
+        data.extend_from_slice(b"\x07\x01\x01");
+    }
+    data.extend_from_slice(b"\xFF\xD9");
+    let estimate = DecodeEstimate::from_raw_data(&data).unwrap();
+    assert_eq!(estimate.format, ImageFormat::J2K);
+    assert_eq!(estimate.channels, 255);
+    assert_eq!(estimate.bytes, 8 * 8 * 255);
+    // every entry
+    let estimate = DecodeEstimate::from_file_path("images/valid/tiff/multi-page.tiff").unwrap();
+    assert_eq!(estimate.frames, 3);
+    assert_eq!(estimate.pixels, 73644);
+    assert_eq!(
+        (estimate.largest_width, estimate.largest_height),
+        (123, 456)
+    );
+
+    // a 1x1 first page in front of a 100000x100000 one
+    let mut data = b"II\x2A\x00\x08\x00\x00\x00".to_vec();
+    data.extend_from_slice(b"\x02\x00");
+    data.extend_from_slice(b"\x00\x01\x03\x00\x01\x00\x00\x00\x01\x00\x00\x00");
+    data.extend_from_slice(b"\x01\x01\x03\x00\x01\x00\x00\x00\x01\x00\x00\x00");
+    data.extend_from_slice(&(data.len() as u32 + 4).to_le_bytes());
+    data.extend_from_slice(b"\x02\x00");
+    data.extend_from_slice(b"\x00\x01\x04\x00\x01\x00\x00\x00\xA0\x86\x01\x00");
+    data.extend_from_slice(b"\x01\x01\x04\x00\x01\x00\x00\x00\xA0\x86\x01\x00");
+    data.extend_from_slice(&[0; 4]);
+    let estimate = DecodeEstimate::from_raw_data(&data).unwrap();
+    assert_eq!(
+        estimate.size,
+        ImageSize {
+            width: 1,
+            height: 1
+        }
+    );
+    assert_eq!(estimate.frames, 2);
+    assert_eq!(estimate.largest_frame_pixels, 100000 * 100000);
+    let policy = DecodePolicy {
+        max_dimension: Some(4800),
+        ..Default::default()
+    };
+    assert_eq!(
+        policy.check(&estimate),
+        Err(PolicyViolation::Dimension {
+            width: 100000,
+            height: 100000,
+            max: 4800,
+        })
+    );
+    let policy = DecodePolicy {
+        max_pixels: Some(16_000_000),
+        ..Default::default()
+    };
+    assert_eq!(
+        policy.check(&estimate),
+        Err(PolicyViolation::Pixels {
+            pixels: 100000 * 100000,
+            max: 16_000_000,
+        })
+    );
+    // 9 mipmap levels from 123x456 down to 1x1
+    let estimate = DecodeEstimate::from_file_path("images/valid/ktx/sample.ktx").unwrap();
+    assert_eq!(estimate.frames, 9);
+    assert_eq!(estimate.pixels, 74520);
+    let estimate = DecodeEstimate::from_file_path("images/valid/gif/animated.gif").unwrap();
+    assert_eq!(estimate.frames, 2);
+    assert_eq!(estimate.bytes, 8 * 6 * 2 * 4);
+
+    // a GIF frame wider than the 2x2 logical screen
+    let mut data = b"GIF89a\x02\x00\x02\x00\x00\x00\x00".to_vec();
+    data.extend_from_slice(b"\x2C\x00\x00\x00\x00\x2C\x01\x02\x00\x00\x02\x00\x3B");
+    let estimate = DecodeEstimate::from_raw_data(&data).unwrap();
+    assert_eq!(estimate.frames, 1);
+    assert_eq!(estimate.pixels, 300 * 2);
+    assert_eq!(estimate.largest_width, 300);
+
+    // an APNG default image before the first fcTL, then an 8x4 frame on a
+    // 4x4 canvas
+    let chunk = |data: &mut Vec<u8>, chunk_type: &[u8], body: &[u8]| {
+        data.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        data.extend_from_slice(chunk_type);
+        data.extend_from_slice(body);
+        data.extend_from_slice(&[0; 4]);
+    };
+    let mut data = b"\x89PNG\r\n\x1A\n".to_vec();
+    chunk(
+        &mut data,
+        b"IHDR",
+        b"\x00\x00\x00\x04\x00\x00\x00\x04\x08\x06\x00\x00\x00",
+    );
+    chunk(&mut data, b"acTL", b"\x00\x00\x00\x01\x00\x00\x00\x00");
+    chunk(&mut data, b"IDAT", b"");
+    let mut fctl = vec![0; 26];
+    fctl[7] = 8;
+    fctl[11] = 4;
+    chunk(&mut data, b"fcTL", &fctl);
+    chunk(&mut data, b"fdAT", b"\x00\x00\x00\x01");
+    chunk(&mut data, b"IEND", b"");
+    let estimate = DecodeEstimate::from_raw_data(&data).unwrap();
+    assert_eq!(estimate.frames, 2);
+    assert_eq!(estimate.pixels, 4 * 4 + 8 * 4);
+    assert_eq!(estimate.largest_width, 8);
+    assert_eq!(estimate.largest_aspect_ratio, 2.0);
+
+    let estimate = DecodeEstimate::from_file_path("images/valid/jpg/very-large.jpg").unwrap();
+    assert_eq!(estimate.bytes, 4800 * 3600 * 3);
+    assert_eq!(DecodePolicy::default().check(&estimate), Ok(()));
+    let policy = DecodePolicy {
+        max_dimension: Some(4800),
+        max_pixels: Some(4800 * 3600),
+        max_frames: Some(1),
+        max_aspect_ratio: Some(1.5),
+        max_bytes: Some(4800 * 3600 * 3),
+    };
+    assert_eq!(policy.check(&estimate), Ok(()));
+
+    let check = |policy: DecodePolicy| policy.check(&estimate).unwrap_err();
+    assert_eq!(
+        check(DecodePolicy {
+            max_dimension: Some(4000),
+            ..Default::default()
+        }),
+        PolicyViolation::Dimension {
+            width: 4800,
+            height: 3600,
+            max: 4000,
+        }
+    );
+    assert_eq!(
+        check(DecodePolicy {
+            max_pixels: Some(16_000_000),
+            ..Default::default()
+        }),
+        PolicyViolation::Pixels {
+            pixels: 17_280_000,
+            max: 16_000_000,
+        }
+    );
+    assert_eq!(
+        check(DecodePolicy {
+            max_aspect_ratio: Some(1.25),
+            ..Default::default()
+        }),
+        PolicyViolation::AspectRatio {
+            ratio: 4800.0 / 3600.0,
+            max: 1.25,
+        }
+    );
+    let violation = check(DecodePolicy {
+        max_bytes: Some(50_000_000),
+        ..Default::default()
+    });
+    assert_eq!(
+        violation,
+        PolicyViolation::Bytes {
+            bytes: 51_840_000,
+            max: 50_000_000,
+        }
+    );
+    assert_eq!(
+        violation.to_string(),
+        "estimated 51840000 decoded bytes exceed the maximum 50000000"
+    );
+
+    let estimate = DecodeEstimate::from_file_path("images/valid/png/sample_apng.png").unwrap();
+    assert_eq!(
+        DecodePolicy {
+            max_frames: Some(30),
+            ..Default::default()
+        }
+        .check(&estimate),
+        Err(PolicyViolation::Frames {
+            frames: 34,
+            max: 30,
+        })
+    );
+
+    assert_unrecognized_err!(DecodeEstimate::from_file_path("images/invalid/sample.png"));
+}
+
 #[test]
 fn test_unrecognized() {
     assert_unrecognized_err!(ImageInfo::from_file_path("images/invalid/sample.png"));
@@ -1083,8 +3327,309 @@ fn test_unrecognized() {
     assert_unrecognized_err!(ImageInfo::from_file_path("images/invalid/crash_hdr_3"));
     assert_unrecognized_err!(ImageInfo::from_file_path("images/invalid/crash_hdr_4"));
     assert_unrecognized_err!(ImageInfo::from_file_path("images/invalid/crash_icns_1"));
-    assert_unrecognized_err!(ImageInfo::from_file_path("images/invalid/crash_icns_2"));
     assert_unrecognized_err!(ImageInfo::from_file_path("images/invalid/crash_png_1"));
     assert_unrecognized_err!(ImageInfo::from_file_path("images/invalid/crash_png_2"));
     assert_unrecognized_err!(ImageInfo::from_file_path("images/invalid/crash_tiff_1"));
+    assert_unrecognized_err!(ImageInfo::from_file_path("images/invalid/truncated_tga"));
+    assert_unrecognized_err!(ImageInfo::from_file_path("images/invalid/qoi_bad_channels"));
+}
+
+fn sample_exif() -> Exif {
+    Exif {
+        orientation: Some(6),
+        make: Some("Canon".to_string()),
+        model: Some("Canon EOS R5".to_string()),
+        date_time_original: Some("2023:05:17 14:32:09".to_string()),
+        exposure_time: Some(Rational {
+            numerator: 1,
+            denominator: 250,
+        }),
+        f_number: Some(Rational {
+            numerator: 28,
+            denominator: 10,
+        }),
+        iso: Some(400),
+        lens_model: Some("RF24-105mm F4 L IS USM".to_string()),
+        gps: Some(GpsCoordinates {
+            latitude: 48.0 + 51.0 / 60.0 + 29.64 / 3600.0,
+            longitude: -(2.0 + 17.0 / 60.0 + 40.2 / 3600.0),
+            altitude: Some(35.0),
+        }),
+    }
+}
+
+#[test]
+fn test_exif() {
+    assert_eq_ok!(
+        Exif::from_file_path("images/valid/jpg/exif.jpg"),
+        Some(sample_exif())
+    );
+    assert_eq_ok!(
+        Exif::from_file_path("images/valid/png/exif.png"),
+        Some(sample_exif())
+    );
+    assert_eq_ok!(
+        Exif::from_file_path("images/valid/webp/exif.webp"),
+        Some(sample_exif())
+    );
+
+    assert_eq_ok!(
+        Exif::from_file_path("images/valid/jpg/1x2-flipped-big-endian.jpg"),
+        Some(Exif {
+            orientation: Some(8),
+            ..Default::default()
+        })
+    );
+    assert_eq_ok!(
+        Exif::from_file_path("images/valid/heic/sample.heic"),
+        Some(Exif {
+            orientation: Some(1),
+            ..Default::default()
+        })
+    );
+    assert_eq_ok!(
+        Exif::from_file_path("images/valid/heic/sample4.heic"),
+        Some(Exif::default())
+    );
+    assert_eq_ok!(
+        Exif::from_file_path("images/valid/tiff/little-endian.tiff"),
+        Some(Exif {
+            orientation: Some(1),
+            ..Default::default()
+        })
+    );
+    assert_eq_ok!(Exif::from_file_path("images/valid/jpg/sample.jpg"), None);
+    assert_eq_ok!(Exif::from_file_path("images/valid/png/sample.png"), None);
+    assert_eq_ok!(Exif::from_file_path("images/valid/bmp/sample.bmp"), None);
+}
+
+#[test]
+fn test_preview() {
+    assert_eq_ok!(
+        Preview::from_file_path("images/valid/jpg/rotation-90.jpg"),
+        vec![Preview {
+            kind: PreviewKind::ExifThumbnail,
+            offset: 114,
+            length: 9525,
+            format: Some(ImageFormat::JPEG),
+            size: Some(ImageSize {
+                width: 160,
+                height: 120
+            }),
+        }]
+    );
+
+    assert_eq_ok!(
+        Preview::from_file_path("images/valid/heic/sample4.heic"),
+        vec![Preview {
+            kind: PreviewKind::HeifThumbnail,
+            offset: 2596,
+            length: 2372,
+            format: None,
+            size: Some(ImageSize {
+                width: 288,
+                height: 512
+            }),
+        }]
+    );
+
+    assert_eq_ok!(
+        Preview::from_file_path("images/valid/tiff/subifd.tiff"),
+        vec![Preview {
+            kind: PreviewKind::TiffReducedImage,
+            offset: 157542,
+            length: 733,
+            format: Some(ImageFormat::JPEG),
+            size: Some(ImageSize {
+                width: 2,
+                height: 1
+            }),
+        }]
+    );
+
+    assert_eq_ok!(
+        Preview::from_file_path("images/valid/psd/thumbnail.psd"),
+        vec![Preview {
+            kind: PreviewKind::PsdThumbnail,
+            offset: 116,
+            length: 733,
+            format: Some(ImageFormat::JPEG),
+            size: Some(ImageSize {
+                width: 2,
+                height: 1
+            }),
+        }]
+    );
+
+    assert_eq_ok!(
+        Preview::from_file_path("images/valid/icns/png.icns"),
+        vec![Preview {
+            kind: PreviewKind::IcnsImage,
+            offset: 40353,
+            length: 4897,
+            format: Some(ImageFormat::PNG),
+            size: Some(ImageSize {
+                width: 64,
+                height: 64
+            }),
+        }]
+    );
+
+    // embedded PNGs of 0xFFFFFFFF x 0xFFFFFFFF
+    assert_eq_ok!(
+        Preview::from_file_path("images/invalid/crash_icns_3"),
+        vec![Preview {
+            kind: PreviewKind::IcnsImage,
+            offset: 60,
+            length: 75,
+            format: Some(ImageFormat::PNG),
+            size: Some(ImageSize {
+                width: 4294967295,
+                height: 4294967295
+            }),
+        }]
+    );
+
+    assert_eq_ok!(
+        Preview::from_file_path("images/valid/jpg/sample.jpg"),
+        vec![]
+    );
+    assert_eq_ok!(
+        Preview::from_file_path("images/valid/tiff/little-endian.tiff"),
+        vec![]
+    );
+}
+
+#[test]
+fn test_hdr_metadata() {
+    assert_eq_ok!(
+        HdrMetadata::from_file_path("images/valid/avif/pq.avif"),
+        HdrMetadata {
+            cicp: Some(Cicp {
+                color_primaries: 9,
+                transfer_characteristics: 16,
+                matrix_coefficients: 9,
+                full_range: false,
+            }),
+            ..Default::default()
+        }
+    );
+
+    assert_eq_ok!(
+        HdrMetadata::from_file_path("images/valid/avif/sample2.avif"),
+        HdrMetadata {
+            cicp: Some(Cicp {
+                color_primaries: 2,
+                transfer_characteristics: 2,
+                matrix_coefficients: 6,
+                full_range: true,
+            }),
+            ..Default::default()
+        }
+    );
+
+    assert_eq_ok!(
+        HdrMetadata::from_file_path("images/valid/avif/grid.avif"),
+        HdrMetadata {
+            gain_map: Some(GainMap {
+                offset: 770,
+                length: 4,
+                size: Some(ImageSize {
+                    width: 500,
+                    height: 400
+                }),
+            }),
+            ..Default::default()
+        }
+    );
+
+    assert_eq_ok!(
+        HdrMetadata::from_file_path("images/valid/png/cicp.png"),
+        HdrMetadata {
+            cicp: Some(Cicp {
+                color_primaries: 9,
+                transfer_characteristics: 16,
+                matrix_coefficients: 0,
+                full_range: true,
+            }),
+            ..Default::default()
+        }
+    );
+
+    assert_eq_ok!(
+        HdrMetadata::from_file_path("images/valid/jpg/ultra-hdr.jpg"),
+        HdrMetadata {
+            gain_map: Some(GainMap {
+                offset: 28798,
+                length: 1002,
+                size: Some(ImageSize {
+                    width: 2,
+                    height: 1
+                }),
+            }),
+            ultra_hdr_version: Some("1.0".to_string()),
+            ..Default::default()
+        }
+    );
+
+    assert_eq_ok!(
+        HdrMetadata::from_file_path("images/valid/jpg/rotation-90.jpg"),
+        HdrMetadata {
+            gain_map: Some(GainMap {
+                offset: 3980332,
+                length: 120638,
+                size: Some(ImageSize {
+                    width: 2016,
+                    height: 1512
+                }),
+            }),
+            ..Default::default()
+        }
+    );
+
+    assert_eq_ok!(
+        HdrMetadata::from_file_path("images/valid/hdr/sample2.hdr"),
+        HdrMetadata {
+            radiance: Some(RadianceHeader {
+                format: Some("32-bit_rle_rgbe".to_string()),
+                exposure: Some(1.0),
+                gamma: Some(1.0),
+                primaries: Some([0.0; 8]),
+                pixel_aspect: None,
+                software: None,
+                orientation: Some(1),
+            }),
+            ..Default::default()
+        }
+    );
+
+    assert_eq_ok!(
+        HdrMetadata::from_file_path("images/valid/hdr/flipped.hdr"),
+        HdrMetadata {
+            radiance: Some(RadianceHeader {
+                format: Some("32-bit_rle_rgbe".to_string()),
+                exposure: Some(10.0),
+                gamma: Some(2.2),
+                primaries: Some([0.64, 0.33, 0.3, 0.6, 0.15, 0.06, 0.3127, 0.329]),
+                pixel_aspect: Some(1.5),
+                software: Some("mkradiance 1.0".to_string()),
+                orientation: Some(5),
+            }),
+            ..Default::default()
+        }
+    );
+
+    assert_eq_ok!(
+        HdrMetadata::from_file_path("images/valid/jpg/sample.jpg"),
+        HdrMetadata::default()
+    );
+
+    assert!(HdrMetadata::from_file_path("images/valid/avif/pq.avif")
+        .unwrap()
+        .is_hdr());
+    assert!(
+        !HdrMetadata::from_file_path("images/valid/avif/sample2.avif")
+            .unwrap()
+            .is_hdr()
+    );
 }